@@ -0,0 +1,205 @@
+use crate::{
+    Error,
+    domain::{ApiScope, Clock, normalize_recovery_code},
+    handlers::basic_authentication,
+    model::{ModelManager, TwoFactor},
+    session_state::TypedSession,
+};
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{
+        HeaderMap,
+        header::{ACCEPT, AUTHORIZATION},
+        request::Parts,
+    },
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+};
+use reqwest::StatusCode;
+use std::sync::Arc;
+use surrealdb::RecordId;
+
+/// The logged in admin user, resolved once by [`reject_anonymous_users`].
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub id: RecordId,
+    pub username: String,
+}
+
+/// Only let requests with a logged in user through, making them available to
+/// handlers as an [`AuthenticatedUser`].
+pub async fn reject_anonymous_users(
+    State(mm): State<Arc<ModelManager>>,
+    session: TypedSession,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let user = match authenticated_user(&mm, &session).await {
+        Ok(user) => user,
+        Err(err) => {
+            tracing::warn!("Failed to load the logged in user: {err:?}");
+            None
+        }
+    };
+
+    match user {
+        Some(user) => {
+            request.extensions_mut().insert(user);
+            next.run(request).await
+        }
+        // Not `Error::Auth`: its Basic challenge would make browsers prompt
+        // for credentials the admin area doesn't accept.
+        None if accepts_json(request.headers()) => Error::Rejected {
+            status: StatusCode::UNAUTHORIZED,
+            code: "UNAUTHORIZED",
+            detail: "Authentication required",
+        }
+        .into_response(),
+        None => Redirect::to("/login").into_response(),
+    }
+}
+
+async fn authenticated_user(
+    mm: &ModelManager,
+    session: &TypedSession,
+) -> crate::Result<Option<AuthenticatedUser>> {
+    let Some(id) = session.get_user_id().await? else {
+        return Ok(None);
+    };
+
+    let username = match session.get_username().await? {
+        Some(username) => username,
+        // Logged in before the username was kept in the session
+        None => {
+            let username = mm.get_username(id.clone()).await?;
+            session.insert_username(username.clone()).await?;
+            username
+        }
+    };
+
+    Ok(Some(AuthenticatedUser { id, username }))
+}
+
+/// The scope a route requires from the API clients calling it.
+#[derive(Debug, Clone)]
+pub struct RequiredScope {
+    mm: Arc<ModelManager>,
+    scope: ApiScope,
+}
+
+impl RequiredScope {
+    pub fn new(mm: Arc<ModelManager>, scope: ApiScope) -> Self {
+        Self { mm, scope }
+    }
+}
+
+/// Only let requests carrying an `Authorization: Bearer` API token with the
+/// required scope through, making its owner available to handlers as an
+/// [`AuthenticatedUser`].
+///
+/// HTTP Basic admin credentials are still accepted, with every scope, until
+/// the existing clients moved to API tokens.
+pub async fn reject_unauthorized_clients(
+    State(required): State<RequiredScope>,
+    mut request: Request,
+    next: Next,
+) -> crate::Result<Response> {
+    let user = match bearer_token(request.headers()) {
+        Some(token) => {
+            let client = required
+                .mm
+                .authenticate_api_token(token)
+                .await?
+                .ok_or(Error::Auth("Unknown, expired or revoked API token".into()))?;
+            if !client.scopes.contains(&required.scope) {
+                return Err(Error::Rejected {
+                    status: StatusCode::FORBIDDEN,
+                    code: "INSUFFICIENT_SCOPE",
+                    detail: "The API token doesn't grant the scope this endpoint requires",
+                });
+            }
+
+            AuthenticatedUser {
+                id: client.user,
+                username: client.username,
+            }
+        }
+        None => {
+            let credentials = basic_authentication(request.headers()).await?;
+            let username = credentials.username.clone();
+            let id = required
+                .mm
+                .validate_credientials(credentials)
+                .await
+                .map_err(|err| Error::Auth(err.to_string()))?;
+            // A password alone doesn't get past the second factor
+            if required.mm.get_two_factor(&id).await?.is_some() {
+                return Err(Error::Auth(format!(
+                    "{username} has two-factor authentication enabled, an API token is required"
+                )));
+            }
+            tracing::warn!("{username} authenticated with Basic credentials instead of a token");
+
+            AuthenticatedUser { id, username }
+        }
+    };
+
+    request.extensions_mut().insert(user);
+    Ok(next.run(request).await)
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+/// Check the second factor of `user_id`, either a code of their
+/// authenticator app or one of their recovery codes, using it up.
+pub async fn verify_second_factor(
+    mm: &ModelManager,
+    user_id: &RecordId,
+    two_factor: &TwoFactor,
+    code: &str,
+    clock: &dyn Clock,
+) -> crate::Result<bool> {
+    match two_factor.secret.verify(code, clock, two_factor.last_step) {
+        Some(step) => mm.use_totp_step(user_id, step).await,
+        None => {
+            mm.use_recovery_code(user_id, &normalize_recovery_code(code))
+                .await
+        }
+    }
+}
+
+fn accepts_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/json"))
+}
+
+impl<S> FromRequestParts<S> for AuthenticatedUser
+where
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<AuthenticatedUser>()
+            .cloned()
+            .ok_or_else(|| {
+                tracing::error!(
+                    "AuthenticatedUser used on a route without `reject_anonymous_users`"
+                );
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            })
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{Error, Result, domain::NewPassword, model::ModelManager};
+use clap::{Parser, Subcommand};
+use rand::{Rng, distr::Alphanumeric};
+use secrecy::{ExposeSecret, SecretString};
+use std::io::{BufRead, Write};
+
+/// Newsletter subscriptions service.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Defaults to `serve` when omitted.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Start the HTTP server and the newsletter delivery worker.
+    Serve,
+    /// Manage the admin users.
+    #[command(subcommand)]
+    User(UserCommand),
+    /// Manage the database schema migrations.
+    #[command(subcommand)]
+    Migrate(MigrateCommand),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum UserCommand {
+    /// Create an admin user.
+    Create {
+        username: String,
+        /// Read the password from the first line of stdin instead of
+        /// generating one.
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// Replace the password of an admin user.
+    ResetPassword {
+        username: String,
+        /// Read the password from the first line of stdin instead of
+        /// generating one.
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// Turn off the two-factor authentication of an admin user who lost
+    /// their authenticator app and recovery codes.
+    ResetTwoFactor { username: String },
+    /// List the admin users.
+    List,
+    /// Delete an admin user.
+    Delete { username: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MigrateCommand {
+    /// Apply the schema definitions and every pending migration.
+    Up,
+    /// Revert the last applied migration.
+    Down {
+        /// Revert every migration applied after this one instead.
+        #[arg(long)]
+        to: Option<String>,
+    },
+    /// List the applied and pending migrations.
+    Status,
+}
+
+impl MigrateCommand {
+    pub async fn run(self, mm: &ModelManager, output: &mut impl Write) -> Result<()> {
+        match self {
+            Self::Up => mm.migrate_up().await?,
+            Self::Down { to } => mm.migrate_down(to.as_deref()).await?,
+            Self::Status => {}
+        }
+
+        let status = mm.migration_status().await?;
+        for name in &status.applied {
+            writeln!(output, "applied\t{name}")?;
+        }
+        for name in &status.pending {
+            writeln!(output, "pending\t{name}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl UserCommand {
+    /// Runs the command, reading passwords from `input` and writing results to `output`.
+    pub async fn run(
+        self,
+        mm: &ModelManager,
+        input: &mut impl BufRead,
+        output: &mut impl Write,
+    ) -> Result<()> {
+        match self {
+            Self::Create {
+                username,
+                password_stdin,
+            } => {
+                let username = username.trim();
+                if username.is_empty() {
+                    return Err(Error::Custom("The username can't be empty".into()));
+                }
+                let (password, generated) = read_or_generate_password(password_stdin, input)?;
+                if !mm.create_user(username, &password).await? {
+                    return Err(Error::Custom(format!(
+                        "A user named `{username}` already exists"
+                    )));
+                }
+                writeln!(output, "Created user `{username}`")?;
+                if generated {
+                    writeln!(output, "Password: {}", password.expose_secret())?;
+                }
+            }
+            Self::ResetPassword {
+                username,
+                password_stdin,
+            } => {
+                let id = mm
+                    .get_user_id(&username)
+                    .await?
+                    .ok_or_else(|| Error::Custom(format!("There is no user named `{username}`")))?;
+                let (password, generated) = read_or_generate_password(password_stdin, input)?;
+                mm.change_password(id, &password).await?;
+                writeln!(output, "Reset the password of `{username}`")?;
+                if generated {
+                    writeln!(output, "Password: {}", password.expose_secret())?;
+                }
+            }
+            Self::ResetTwoFactor { username } => {
+                if !mm.reset_two_factor(&username).await? {
+                    return Err(Error::Custom(format!(
+                        "There is no user named `{username}`"
+                    )));
+                }
+                writeln!(
+                    output,
+                    "Reset the two-factor authentication of `{username}`"
+                )?;
+            }
+            Self::List => {
+                for user in mm.list_users().await? {
+                    writeln!(output, "{}\t{}", user.username, user.created_at)?;
+                }
+            }
+            Self::Delete { username } => {
+                if !mm.delete_user(&username).await? {
+                    return Err(Error::Custom(format!(
+                        "There is no user named `{username}`"
+                    )));
+                }
+                writeln!(output, "Deleted user `{username}`")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the password and whether it was generated, in which case it has
+/// to be shown to the operator.
+fn read_or_generate_password(
+    from_input: bool,
+    input: &mut impl BufRead,
+) -> Result<(NewPassword, bool)> {
+    if from_input {
+        let mut line = String::new();
+        input.read_line(&mut line)?;
+        let password = line.trim_end_matches(['\r', '\n']).to_string();
+        return Ok((NewPassword::try_from(SecretString::from(password))?, false));
+    }
+
+    // Random alphanumeric passwords almost always mix three character
+    // classes, draw again in the rare case they don't.
+    loop {
+        let password: String = rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(24)
+            .map(char::from)
+            .collect();
+        if let Ok(password) = NewPassword::try_from(SecretString::from(password)) {
+            return Ok((password, true));
+        }
+    }
+}
@@ -2,7 +2,7 @@ use crate::domain::SubscriberEmail;
 use config::ConfigError;
 use secrecy::SecretString;
 use serde::Deserialize;
-use std::time::Duration;
+use std::{path::PathBuf, time::Duration};
 use url::Url;
 
 #[derive(Debug, Deserialize, Clone)]
@@ -13,15 +13,162 @@ pub struct Config {
     pub hmac_secret: SecretString,
     pub database: DatabaseConfig,
     pub email_client: EmailClientConfig,
+    #[serde(default)]
+    pub delivery: DeliveryConfig,
+    #[serde(default)]
+    pub confirmation: ConfirmationConfig,
+    #[serde(default)]
+    pub idempotency: IdempotencyConfig,
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub tracing: TracingConfig,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct EmailClientConfig {
     pub sender_email: SubscriberEmail,
-    pub base_url: Url,
-    pub auth_token: SecretString,
     #[serde(with = "serde_humantime")]
     pub timeout: Duration,
+    pub provider: EmailProviderConfig,
+}
+
+/// The email backend, e.g. `SUBSCRIPTIONS__EMAIL_CLIENT__PROVIDER__SMTP__HOST=...`
+/// or `SUBSCRIPTIONS__EMAIL_CLIENT__PROVIDER=stdout`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailProviderConfig {
+    Postmark {
+        base_url: Url,
+        auth_token: SecretString,
+    },
+    Smtp {
+        host: String,
+        port: u16,
+        tls: SmtpTls,
+        username: Option<String>,
+        password: Option<SecretString>,
+    },
+    /// Write every email as an `.eml` file in `directory`.
+    File { directory: PathBuf },
+    /// Print every email on stdout.
+    Stdout,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpTls {
+    None,
+    Starttls,
+    Tls,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct DeliveryConfig {
+    /// Total number of attempts before a delivery is dead-lettered.
+    pub max_attempts: u32,
+    #[serde(with = "serde_humantime")]
+    pub initial_backoff: Duration,
+    #[serde(with = "serde_humantime")]
+    pub max_backoff: Duration,
+    /// Fraction of the backoff randomly added or removed, between `0` and `1`.
+    pub jitter: f64,
+}
+
+impl Default for DeliveryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(60 * 60),
+            jitter: 0.2,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ConfirmationConfig {
+    /// How long a confirmation link stays valid after it was emailed.
+    #[serde(with = "serde_humantime")]
+    pub token_ttl: Duration,
+}
+
+impl Default for ConfirmationConfig {
+    fn default() -> Self {
+        Self {
+            token_ttl: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct IdempotencyConfig {
+    /// How long the response to an idempotency key is replayed, after which
+    /// the key is forgotten.
+    #[serde(with = "serde_humantime")]
+    pub key_ttl: Duration,
+    /// How long a request may hold a key without saving its response, before
+    /// a retry takes the key over, e.g. after a crash.
+    #[serde(with = "serde_humantime")]
+    pub claim_ttl: Duration,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            key_ttl: Duration::from_secs(24 * 60 * 60),
+            claim_ttl: Duration::from_secs(2 * 60),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ShutdownConfig {
+    /// How long in-flight requests and deliveries get to finish once a
+    /// shutdown signal is received.
+    #[serde(with = "serde_humantime")]
+    pub timeout: Duration,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct MetricsConfig {
+    /// Port of a separate server of `/metrics`, to keep it off the public
+    /// network. When unset, `/metrics` is served on the application port to
+    /// API clients with the `metrics:read` scope.
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct TracingConfig {
+    /// OTLP/HTTP collector to export traces to, e.g. `http://collector:4318`.
+    /// Traces don't leave the process when unset.
+    pub otlp_endpoint: Option<Url>,
+    pub service_name: String,
+}
+
+impl Default for TracingConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: env!("CARGO_PKG_NAME").into(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -31,13 +178,95 @@ pub struct DatabaseConfig {
     pub password: secrecy::SecretString,
     pub namespace: String,
     pub name: String,
+    /// Apply pending migrations at startup, the default. Turn off when several
+    /// replicas share the database and migrations run as a separate
+    /// `migrate up` step.
+    #[serde(default = "enabled")]
+    pub auto_migrate: bool,
+}
+
+fn enabled() -> bool {
+    true
 }
 
 impl Config {
     pub fn load() -> Result<Self, ConfigError> {
-        config::Config::builder()
-            .add_source(config::Environment::with_prefix(env!("CARGO_PKG_NAME")).separator("__"))
-            .build()?
-            .try_deserialize()
+        environment()?.try_deserialize()
+    }
+}
+
+impl DatabaseConfig {
+    /// Load only the database settings, for the commands that don't serve
+    /// the application.
+    pub fn load() -> Result<Self, ConfigError> {
+        environment()?.get("database")
+    }
+}
+
+impl TracingConfig {
+    /// Load only the tracing settings, falling back to the defaults.
+    pub fn load() -> Result<Self, ConfigError> {
+        match environment()?.get("tracing") {
+            Err(ConfigError::NotFound(_)) => Ok(Self::default()),
+            result => result,
+        }
+    }
+}
+
+/// Settings from `SUBSCRIPTIONS__*` environment variables, nested with `__`.
+fn environment() -> Result<config::Config, ConfigError> {
+    config::Config::builder()
+        .add_source(config::Environment::with_prefix(env!("CARGO_PKG_NAME")).separator("__"))
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use claims::assert_ok;
+    use std::time::Duration;
+
+    /// Only the settings without a default.
+    fn required_settings() -> config::ConfigBuilder<config::builder::DefaultState> {
+        [
+            ("port", "1337"),
+            ("host", "0.0.0.0"),
+            ("base_url", "http://localhost:1337"),
+            ("hmac_secret", "secret"),
+            ("database.base_url", "mem://"),
+            ("database.username", "subscriptions"),
+            ("database.password", "password"),
+            ("database.namespace", "main"),
+            ("database.name", "db"),
+            ("email_client.sender_email", "admin@example.com"),
+            ("email_client.timeout", "2s"),
+            ("email_client.provider", "stdout"),
+        ]
+        .into_iter()
+        .fold(config::Config::builder(), |builder, (key, value)| {
+            builder.set_override(key, value).unwrap()
+        })
+    }
+
+    #[test]
+    fn optional_sections_fall_back_to_their_defaults() {
+        let config = assert_ok!(
+            required_settings()
+                .build()
+                .and_then(|config| config.try_deserialize::<Config>())
+        );
+
+        assert!(config.database.auto_migrate);
+        assert_eq!(config.delivery.max_attempts, 5);
+        assert_eq!(config.delivery.initial_backoff, Duration::from_secs(30));
+        assert_eq!(config.delivery.max_backoff, Duration::from_secs(3600));
+        assert_eq!(
+            config.confirmation.token_ttl,
+            Duration::from_secs(24 * 3600)
+        );
+        assert_eq!(config.idempotency.key_ttl, Duration::from_secs(24 * 3600));
+        assert_eq!(config.idempotency.claim_ttl, Duration::from_secs(120));
+        assert_eq!(config.shutdown.timeout, Duration::from_secs(30));
+        assert_eq!(config.metrics.port, None);
     }
 }
@@ -0,0 +1,196 @@
+use rand::{Rng, distr::Alphanumeric};
+use serde::{Deserialize, Serialize};
+use std::{str::FromStr, time::Duration};
+use validator::ValidationError;
+
+/// Prefix of every API token, making leaked ones easy to spot.
+const TOKEN_PREFIX: &str = "subs_";
+
+/// What an API token allows its holder to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApiScope {
+    #[serde(rename = "newsletter:publish")]
+    NewsletterPublish,
+    #[serde(rename = "subscribers:read")]
+    SubscribersRead,
+    #[serde(rename = "metrics:read")]
+    MetricsRead,
+}
+
+impl ApiScope {
+    pub const ALL: [Self; 3] = [
+        Self::NewsletterPublish,
+        Self::SubscribersRead,
+        Self::MetricsRead,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NewsletterPublish => "newsletter:publish",
+            Self::SubscribersRead => "subscribers:read",
+            Self::MetricsRead => "metrics:read",
+        }
+    }
+}
+
+impl FromStr for ApiScope {
+    type Err = ValidationError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == value)
+            .ok_or_else(|| {
+                ValidationError::new("INVALID_API_SCOPE")
+                    .with_message(format!("`{value}` is not a known scope").into())
+            })
+    }
+}
+
+/// An API token about to be issued.
+#[derive(Debug)]
+pub struct NewApiToken {
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+    /// `None` for a token that never expires.
+    pub expires_in: Option<Duration>,
+}
+
+impl NewApiToken {
+    /// Parse the fields of the token creation form, a blank `expires_in_days`
+    /// meaning the token never expires.
+    pub fn parse(
+        name: &str,
+        scopes: &[String],
+        expires_in_days: &str,
+    ) -> Result<Self, ValidationError> {
+        let name = name.trim();
+        let max_length = 100;
+        if name.is_empty() || name.chars().count() > max_length {
+            return Err(ValidationError::new("INVALID_API_TOKEN_NAME").with_message(
+                format!("The name must be between 1 and {max_length} characters long").into(),
+            ));
+        }
+
+        let mut parsed_scopes = Vec::new();
+        for scope in scopes {
+            let scope = scope.parse::<ApiScope>()?;
+            if !parsed_scopes.contains(&scope) {
+                parsed_scopes.push(scope);
+            }
+        }
+        if parsed_scopes.is_empty() {
+            return Err(ValidationError::new("INVALID_API_SCOPE")
+                .with_message("At least one scope must be granted".into()));
+        }
+
+        let expires_in = match expires_in_days.trim() {
+            "" => None,
+            days => match days.parse::<u64>() {
+                Ok(days @ 1..=3650) => Some(Duration::from_secs(days * 24 * 60 * 60)),
+                _ => {
+                    return Err(ValidationError::new("INVALID_API_TOKEN_EXPIRY")
+                        .with_message("The expiry must be between 1 and 3650 days".into()));
+                }
+            },
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            scopes: parsed_scopes,
+            expires_in,
+        })
+    }
+}
+
+/// Generate the secret handed to the client, only its hash is stored.
+pub fn generate_api_token() -> String {
+    let secret = rand::rng()
+        .sample_iter(&Alphanumeric)
+        .map(char::from)
+        .take(40)
+        .collect::<String>();
+    format!("{TOKEN_PREFIX}{secret}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApiScope, NewApiToken, generate_api_token};
+    use claims::{assert_err, assert_none, assert_ok};
+    use std::time::Duration;
+
+    fn scopes(scopes: &[&str]) -> Vec<String> {
+        scopes.iter().map(|scope| scope.to_string()).collect()
+    }
+
+    #[test]
+    fn a_blank_name_is_rejected() {
+        assert_err!(NewApiToken::parse(
+            "  ",
+            &scopes(&["newsletter:publish"]),
+            ""
+        ));
+    }
+
+    #[test]
+    fn a_token_without_scopes_is_rejected() {
+        assert_err!(NewApiToken::parse("ci", &[], ""));
+    }
+
+    #[test]
+    fn unknown_scopes_are_rejected() {
+        assert_err!(NewApiToken::parse("ci", &scopes(&["admin:all"]), ""));
+    }
+
+    #[test]
+    fn repeated_scopes_are_granted_once() {
+        let token = assert_ok!(NewApiToken::parse(
+            "ci",
+            &scopes(&["subscribers:read", "subscribers:read"]),
+            ""
+        ));
+        assert_eq!(token.scopes, vec![ApiScope::SubscribersRead]);
+    }
+
+    #[test]
+    fn a_blank_expiry_never_expires() {
+        let token = assert_ok!(NewApiToken::parse(
+            "ci",
+            &scopes(&["newsletter:publish"]),
+            ""
+        ));
+        assert_none!(token.expires_in);
+    }
+
+    #[test]
+    fn the_expiry_is_given_in_days() {
+        let token = assert_ok!(NewApiToken::parse(
+            "ci",
+            &scopes(&["newsletter:publish"]),
+            "30"
+        ));
+        assert_eq!(
+            token.expires_in,
+            Some(Duration::from_secs(30 * 24 * 60 * 60))
+        );
+    }
+
+    #[test]
+    fn a_zero_or_invalid_expiry_is_rejected() {
+        for days in ["0", "-1", "soon", "3651"] {
+            assert_err!(NewApiToken::parse(
+                "ci",
+                &scopes(&["newsletter:publish"]),
+                days
+            ));
+        }
+    }
+
+    #[test]
+    fn generated_tokens_are_prefixed_and_unique() {
+        let token = generate_api_token();
+        assert!(token.starts_with("subs_"));
+        assert_eq!(token.len(), 45);
+        assert_ne!(token, generate_api_token());
+    }
+}
@@ -0,0 +1,53 @@
+use validator::ValidationError;
+
+#[derive(Debug, Clone)]
+pub struct IdempotencyKey(String);
+
+impl AsRef<str> for IdempotencyKey {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for IdempotencyKey {
+    type Error = validator::ValidationError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if value.is_empty() {
+            return Err(ValidationError::new("INVALID_IDEMPOTENCY_KEY")
+                .with_message("idempotency key is empty".into()));
+        }
+
+        let max_length = 50;
+        if value.len() > max_length {
+            return Err(ValidationError::new("INVALID_IDEMPOTENCY_KEY")
+                .with_message(format!("idempotency key length is more than {max_length}").into()));
+        }
+
+        Ok(Self(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IdempotencyKey;
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn empty_string_is_rejected() {
+        let key = "".to_string();
+        assert_err!(IdempotencyKey::try_from(key));
+    }
+
+    #[test]
+    fn a_key_longer_than_50_characters_is_rejected() {
+        let key = "a".repeat(51);
+        assert_err!(IdempotencyKey::try_from(key));
+    }
+
+    #[test]
+    fn a_50_characters_long_key_is_valid() {
+        let key = "a".repeat(50);
+        assert_ok!(IdempotencyKey::try_from(key));
+    }
+}
@@ -1,4 +1,12 @@
+mod api_token;
+mod idempotency_key;
+mod new_password;
 mod subscriber;
+mod totp;
 
+pub use api_token::{ApiScope, NewApiToken, generate_api_token};
+pub use idempotency_key::IdempotencyKey;
+pub use new_password::NewPassword;
 pub use subscriber::Subscriber;
 pub use subscriber::SubscriberEmail;
+pub use totp::{Clock, SystemClock, TotpSecret, generate_recovery_codes, normalize_recovery_code};
@@ -0,0 +1,102 @@
+use secrecy::{ExposeSecret, SecretString};
+use validator::ValidationError;
+
+/// A password strong enough to be stored for an admin user.
+#[derive(Debug)]
+pub struct NewPassword(SecretString);
+
+impl NewPassword {
+    const MIN_LENGTH: usize = 12;
+    const MAX_LENGTH: usize = 128;
+    /// Out of lowercase letters, uppercase letters, digits and symbols.
+    const MIN_CHARACTER_CLASSES: usize = 3;
+}
+
+impl ExposeSecret<str> for NewPassword {
+    fn expose_secret(&self) -> &str {
+        self.0.expose_secret()
+    }
+}
+
+impl TryFrom<SecretString> for NewPassword {
+    type Error = validator::ValidationError;
+
+    fn try_from(value: SecretString) -> Result<Self, Self::Error> {
+        let password = value.expose_secret();
+        let length = password.chars().count();
+
+        if length < Self::MIN_LENGTH {
+            return Err(ValidationError::new("WEAK_PASSWORD").with_message(
+                format!(
+                    "The new password must be at least {} characters long",
+                    Self::MIN_LENGTH
+                )
+                .into(),
+            ));
+        }
+
+        if length > Self::MAX_LENGTH {
+            return Err(ValidationError::new("INVALID_PASSWORD").with_message(
+                format!(
+                    "The new password must be at most {} characters long",
+                    Self::MAX_LENGTH
+                )
+                .into(),
+            ));
+        }
+
+        let character_classes = [
+            password.chars().any(|c| c.is_lowercase()),
+            password.chars().any(|c| c.is_uppercase()),
+            password.chars().any(|c| c.is_numeric()),
+            password.chars().any(|c| !c.is_alphanumeric()),
+        ]
+        .into_iter()
+        .filter(|present| *present)
+        .count();
+
+        if character_classes < Self::MIN_CHARACTER_CLASSES {
+            return Err(ValidationError::new("WEAK_PASSWORD").with_message(
+                "The new password must mix at least three of lowercase letters, uppercase letters, digits and symbols".into(),
+            ));
+        }
+
+        Ok(Self(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NewPassword;
+    use claims::{assert_err, assert_ok};
+    use secrecy::SecretString;
+
+    fn password(value: &str) -> SecretString {
+        SecretString::new(value.into())
+    }
+
+    #[test]
+    fn a_password_shorter_than_12_characters_is_rejected() {
+        assert_err!(NewPassword::try_from(password("Sh0rt!pass")));
+    }
+
+    #[test]
+    fn a_password_longer_than_128_characters_is_rejected() {
+        assert_err!(NewPassword::try_from(password(&"aA1!".repeat(33))));
+    }
+
+    #[test]
+    fn a_password_with_a_single_character_class_is_rejected() {
+        assert_err!(NewPassword::try_from(password("onlylowercaseletters")));
+    }
+
+    #[test]
+    fn a_password_with_two_character_classes_is_rejected() {
+        assert_err!(NewPassword::try_from(password("lowercaseand1234")));
+    }
+
+    #[test]
+    fn a_long_password_mixing_three_character_classes_is_valid() {
+        assert_ok!(NewPassword::try_from(password("Correct-horse-battery")));
+    }
+}
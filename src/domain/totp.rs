@@ -0,0 +1,224 @@
+use qrcode::{QrCode, render::svg};
+use rand::{Rng, distr::Alphanumeric};
+use std::time::{SystemTime, UNIX_EPOCH};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+/// Seconds each code stays valid for, as expected by authenticator apps.
+const STEP: u64 = 30;
+const DIGITS: usize = 6;
+/// Codes of the previous and next steps are accepted too, for clock drift.
+const SKEW: u64 = 1;
+const ISSUER: &str = env!("CARGO_PKG_NAME");
+const RECOVERY_CODES: usize = 10;
+
+/// Tells the time codes are checked at.
+pub trait Clock: std::fmt::Debug + Send + Sync {
+    /// Seconds since the Unix epoch.
+    fn now(&self) -> u64;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default()
+    }
+}
+
+/// The base32 encoded key shared with the authenticator app of a user.
+#[derive(Clone)]
+pub struct TotpSecret(String);
+
+impl std::fmt::Debug for TotpSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("TotpSecret([REDACTED])")
+    }
+}
+
+impl TotpSecret {
+    pub fn generate() -> Self {
+        let key = rand::rng().random::<[u8; 20]>().to_vec();
+        match Secret::Raw(key).to_encoded() {
+            Secret::Encoded(encoded) => Self(encoded),
+            Secret::Raw(_) => unreachable!("Expected an encoded secret"),
+        }
+    }
+
+    /// Wrap a secret read back from the database.
+    pub fn from_base32(encoded: String) -> Self {
+        Self(encoded)
+    }
+
+    pub fn as_base32(&self) -> &str {
+        &self.0
+    }
+
+    /// The URI authenticator apps enroll from, labelled with `account`.
+    pub fn otpauth_uri(&self, account: &str) -> String {
+        self.totp(account).get_url()
+    }
+
+    /// The `otpauth` URI as a QR code, ready to be inlined in a page.
+    pub fn qr_code_svg(&self, account: &str) -> String {
+        QrCode::new(self.otpauth_uri(account))
+            .map(|code| code.render::<svg::Color>().min_dimensions(200, 200).build())
+            .unwrap_or_default()
+    }
+
+    /// The code shown by authenticator apps at `time`.
+    pub fn code_at(&self, time: u64) -> String {
+        self.totp("").generate(time)
+    }
+
+    /// Check `code` against the time steps around the `clock`, ignoring
+    /// those up to `last_step` so a code can't be replayed.
+    ///
+    /// Returns the step the code belongs to when it's valid.
+    pub fn verify(&self, code: &str, clock: &dyn Clock, last_step: Option<u64>) -> Option<u64> {
+        let code = code.trim();
+        if code.len() != DIGITS || !code.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+
+        let totp = self.totp("");
+        let current_step = clock.now() / STEP;
+        (current_step.saturating_sub(SKEW)..=current_step + SKEW)
+            .filter(|step| last_step.is_none_or(|last_step| *step > last_step))
+            .find(|step| totp.check(code, step * STEP))
+    }
+
+    /// Steps are checked one by one, hence no skew.
+    fn totp(&self, account: &str) -> TOTP {
+        let key = Secret::Encoded(self.0.clone())
+            .to_bytes()
+            .unwrap_or_default();
+        TOTP::new_unchecked(
+            Algorithm::SHA1,
+            DIGITS,
+            0,
+            STEP,
+            key,
+            Some(ISSUER.into()),
+            account.into(),
+        )
+    }
+}
+
+/// Single use codes letting a user in without their authenticator app, only
+/// their hashes are stored.
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODES)
+        .map(|_| {
+            let code = rand::rng()
+                .sample_iter(&Alphanumeric)
+                .map(|byte| char::from(byte).to_ascii_lowercase())
+                .take(10)
+                .collect::<String>();
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect()
+}
+
+/// Recovery codes are matched regardless of case and surrounding blanks.
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.trim().to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Clock, TotpSecret, generate_recovery_codes, normalize_recovery_code};
+    use claims::{assert_none, assert_some_eq};
+    use std::collections::HashSet;
+
+    /// A clock stopped at a given second.
+    #[derive(Debug)]
+    struct FakeClock(u64);
+
+    impl Clock for FakeClock {
+        fn now(&self) -> u64 {
+            self.0
+        }
+    }
+
+    /// The RFC 6238 test key, `12345678901234567890` in base32.
+    fn rfc_secret() -> TotpSecret {
+        TotpSecret::from_base32("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".into())
+    }
+
+    #[test]
+    fn codes_match_the_rfc_6238_test_vectors() {
+        let secret = rfc_secret();
+        assert_eq!(secret.code_at(59), "287082");
+        assert_eq!(secret.code_at(1_111_111_109), "081804");
+        assert_eq!(secret.code_at(2_000_000_000), "279037");
+    }
+
+    #[test]
+    fn the_current_code_is_accepted() {
+        let clock = FakeClock(1_111_111_109);
+        assert_some_eq!(rfc_secret().verify("081804", &clock, None), 37_037_036);
+    }
+
+    #[test]
+    fn codes_of_adjacent_steps_are_accepted() {
+        let secret = rfc_secret();
+        let clock = FakeClock(1_000_000_000);
+        let previous = secret.code_at(clock.now() - 30);
+        let next = secret.code_at(clock.now() + 30);
+
+        assert!(secret.verify(&previous, &clock, None).is_some());
+        assert!(secret.verify(&next, &clock, None).is_some());
+    }
+
+    #[test]
+    fn codes_older_than_the_skew_are_rejected() {
+        let secret = rfc_secret();
+        let clock = FakeClock(1_000_000_000);
+        let stale = secret.code_at(clock.now() - 90);
+
+        assert_none!(secret.verify(&stale, &clock, None));
+    }
+
+    #[test]
+    fn a_used_code_cant_be_replayed() {
+        let secret = rfc_secret();
+        let clock = FakeClock(1_000_000_000);
+        let code = secret.code_at(clock.now());
+        let step = secret.verify(&code, &clock, None).unwrap();
+
+        assert_none!(secret.verify(&code, &clock, Some(step)));
+    }
+
+    #[test]
+    fn malformed_codes_are_rejected() {
+        let clock = FakeClock(59);
+        for code in ["", "28708", "2870822", "28708a", "287 082"] {
+            assert_none!(rfc_secret().verify(code, &clock, None));
+        }
+    }
+
+    #[test]
+    fn generated_secrets_round_trip_through_the_otpauth_uri() {
+        let secret = TotpSecret::generate();
+        let uri = secret.otpauth_uri("admin");
+
+        assert!(uri.starts_with("otpauth://totp/subscriptions:admin?"));
+        assert!(uri.contains(&format!("secret={}", secret.as_base32())));
+        assert!(secret.qr_code_svg("admin").contains("<svg"));
+    }
+
+    #[test]
+    fn recovery_codes_are_unique_and_normalized() {
+        let codes = generate_recovery_codes();
+
+        assert_eq!(codes.len(), 10);
+        assert_eq!(codes.iter().collect::<HashSet<_>>().len(), 10);
+        for code in &codes {
+            assert_eq!(&normalize_recovery_code(&code.to_uppercase()), code);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use super::{EmailHeader, EmailSender, SendEmailError, build_message};
+use crate::domain::SubscriberEmail;
+use lettre::{AsyncFileTransport, AsyncTransport, Tokio1Executor};
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+
+/// Writes emails to disk or stdout instead of sending them, for local
+/// development and environments without an email provider.
+#[derive(Debug)]
+pub struct FileSink {
+    target: Target,
+    sender_email: SubscriberEmail,
+}
+
+#[derive(Debug)]
+enum Target {
+    /// One `.eml` file per email.
+    Directory {
+        transport: AsyncFileTransport<Tokio1Executor>,
+        path: PathBuf,
+    },
+    Stdout,
+}
+
+impl FileSink {
+    pub fn directory(sender_email: SubscriberEmail, directory: PathBuf) -> Self {
+        Self {
+            target: Target::Directory {
+                transport: AsyncFileTransport::new(&directory),
+                path: directory,
+            },
+            sender_email,
+        }
+    }
+
+    pub fn stdout(sender_email: SubscriberEmail) -> Self {
+        Self {
+            target: Target::Stdout,
+            sender_email,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailSender for FileSink {
+    async fn send_email_with_headers(
+        &self,
+        recipeint: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[EmailHeader],
+    ) -> Result<(), SendEmailError> {
+        let message = build_message(
+            &self.sender_email,
+            recipeint,
+            subject,
+            html_content,
+            text_content,
+            headers,
+        )?;
+
+        match &self.target {
+            Target::Directory { transport, .. } => {
+                let id = transport
+                    .send(message)
+                    .await
+                    .map_err(|err| SendEmailError::Transient(Box::new(err)))?;
+                tracing::info!("Wrote email {id} to disk");
+            }
+            Target::Stdout => {
+                let mut stdout = tokio::io::stdout();
+                let mut formatted = message.formatted();
+                formatted.extend_from_slice(b"\n");
+                stdout
+                    .write_all(&formatted)
+                    .await
+                    .map_err(|err| SendEmailError::Transient(Box::new(err)))?;
+                stdout
+                    .flush()
+                    .await
+                    .map_err(|err| SendEmailError::Transient(Box::new(err)))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn check_health(&self) -> Result<(), SendEmailError> {
+        let Target::Directory { path, .. } = &self.target else {
+            return Ok(());
+        };
+
+        let metadata = tokio::fs::metadata(path)
+            .await
+            .map_err(|err| SendEmailError::Transient(Box::new(err)))?;
+        if !metadata.is_dir() || metadata.permissions().readonly() {
+            return Err(SendEmailError::Permanent(
+                format!("{} is not a writable directory", path.display()).into(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_err, assert_ok};
+    use fake::Fake;
+    use fake::faker::internet::en::SafeEmail;
+
+    #[tokio::test]
+    async fn send_email_writes_an_eml_file_per_email() {
+        // Arrange
+        let directory = std::env::temp_dir().join(format!("emails-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let sink = FileSink::directory(email(), directory.clone());
+        let recipeint = email();
+
+        // Act
+        let outcome = sink
+            .send_email(&recipeint, "Newsletter title", "<p>html</p>", "plain text")
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+        let files = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].extension().unwrap(), "eml");
+        let content = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(content.contains(&format!("To: {}", recipeint.as_ref())));
+        assert!(content.contains("Subject: Newsletter title"));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn check_health_fails_if_the_directory_is_missing() {
+        // Arrange
+        let directory = std::env::temp_dir().join(format!("emails-{}", rand::random::<u64>()));
+        let sink = FileSink::directory(email(), directory);
+
+        // Act
+        let outcome = sink.check_health().await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+
+    /// Generate a random subscriber email
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::try_from(SafeEmail().fake::<String>())
+            .expect("Expect to get valid subscriber email!")
+    }
+}
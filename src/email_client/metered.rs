@@ -0,0 +1,54 @@
+use super::{EmailHeader, EmailSender, SendEmailError};
+use crate::domain::SubscriberEmail;
+use std::time::Instant;
+
+/// Records the sends, failures and latency of another backend, labelled with
+/// the name of its provider.
+#[derive(Debug)]
+pub struct MeteredEmailSender<S> {
+    inner: S,
+    provider: &'static str,
+}
+
+impl<S> MeteredEmailSender<S> {
+    pub fn new(provider: &'static str, inner: S) -> Self {
+        Self { inner, provider }
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: EmailSender> EmailSender for MeteredEmailSender<S> {
+    async fn send_email_with_headers(
+        &self,
+        recipeint: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[EmailHeader],
+    ) -> Result<(), SendEmailError> {
+        let started_at = Instant::now();
+        let outcome = self
+            .inner
+            .send_email_with_headers(recipeint, subject, html_content, text_content, headers)
+            .await;
+
+        metrics::counter!("email_sends_total", "provider" => self.provider).increment(1);
+        metrics::histogram!("email_send_duration_seconds", "provider" => self.provider)
+            .record(started_at.elapsed().as_secs_f64());
+        if let Err(err) = &outcome {
+            let retryable = if err.is_retryable() { "true" } else { "false" };
+            metrics::counter!(
+                "email_send_failures_total",
+                "provider" => self.provider,
+                "retryable" => retryable
+            )
+            .increment(1);
+        }
+
+        outcome
+    }
+
+    async fn check_health(&self) -> Result<(), SendEmailError> {
+        self.inner.check_health().await
+    }
+}
@@ -0,0 +1,158 @@
+mod file;
+mod metered;
+mod postmark;
+mod smtp;
+
+pub use file::FileSink;
+pub use metered::MeteredEmailSender;
+pub use postmark::PostmarkClient;
+pub use smtp::SmtpClient;
+
+use crate::{
+    config::{EmailClientConfig, EmailProviderConfig},
+    domain::SubscriberEmail,
+};
+use lettre::{
+    Message,
+    message::{
+        MultiPart,
+        header::{HeaderName, HeaderValue},
+    },
+};
+use std::{error::Error as StdError, fmt::Debug, sync::Arc};
+
+/// A way of delivering emails, picked through [`EmailClientConfig::provider`].
+#[async_trait::async_trait]
+pub trait EmailSender: Debug + Send + Sync {
+    async fn send_email_with_headers(
+        &self,
+        recipeint: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[EmailHeader],
+    ) -> Result<(), SendEmailError>;
+
+    async fn send_email(
+        &self,
+        recipeint: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+    ) -> Result<(), SendEmailError> {
+        self.send_email_with_headers(recipeint, subject, html_content, text_content, &[])
+            .await
+    }
+
+    /// Make sure emails could be sent right now, e.g. that the provider is
+    /// reachable and accepts our credentials, without sending any.
+    async fn check_health(&self) -> Result<(), SendEmailError>;
+}
+
+/// Build the email backend selected in the configuration.
+pub fn build_email_client(config: EmailClientConfig) -> crate::Result<Arc<dyn EmailSender>> {
+    Ok(match config.provider {
+        EmailProviderConfig::Postmark {
+            base_url,
+            auth_token,
+        } => Arc::new(MeteredEmailSender::new(
+            "postmark",
+            PostmarkClient::new(config.sender_email, base_url, auth_token, config.timeout)?,
+        )),
+        EmailProviderConfig::Smtp {
+            host,
+            port,
+            tls,
+            username,
+            password,
+        } => Arc::new(MeteredEmailSender::new(
+            "smtp",
+            SmtpClient::new(
+                config.sender_email,
+                &host,
+                port,
+                tls,
+                username.zip(password),
+                config.timeout,
+            )?,
+        )),
+        EmailProviderConfig::File { directory } => Arc::new(MeteredEmailSender::new(
+            "file",
+            FileSink::directory(config.sender_email, directory),
+        )),
+        EmailProviderConfig::Stdout => Arc::new(MeteredEmailSender::new(
+            "stdout",
+            FileSink::stdout(config.sender_email),
+        )),
+    })
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct EmailHeader {
+    pub name: String,
+    pub value: String,
+}
+
+impl EmailHeader {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+}
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SendEmailError {
+    /// The provider may accept the email later (timeouts, 429, 5xx).
+    #[error("Transient email delivery failure: {0}")]
+    Transient(#[source] BoxError),
+    /// Sending the same email again won't help (e.g. an invalid recipient).
+    #[error("Permanent email delivery failure: {0}")]
+    Permanent(#[source] BoxError),
+}
+
+impl SendEmailError {
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::Transient(_))
+    }
+}
+
+/// Build a multipart MIME message, shared by the backends speaking raw email.
+fn build_message(
+    sender: &SubscriberEmail,
+    recipeint: &SubscriberEmail,
+    subject: &str,
+    html_content: &str,
+    text_content: &str,
+    headers: &[EmailHeader],
+) -> Result<Message, SendEmailError> {
+    let mut builder = Message::builder()
+        .from(
+            sender
+                .as_ref()
+                .parse()
+                .map_err(|err| SendEmailError::Permanent(Box::new(err)))?,
+        )
+        .to(recipeint
+            .as_ref()
+            .parse()
+            .map_err(|err| SendEmailError::Permanent(Box::new(err)))?)
+        .subject(subject);
+
+    for header in headers {
+        let name = HeaderName::new_from_ascii(header.name.clone())
+            .map_err(|err| SendEmailError::Permanent(Box::new(err)))?;
+        builder = builder.raw_header(HeaderValue::new(name, header.value.clone()));
+    }
+
+    builder
+        .multipart(MultiPart::alternative_plain_html(
+            text_content.to_owned(),
+            html_content.to_owned(),
+        ))
+        .map_err(|err| SendEmailError::Permanent(Box::new(err)))
+}
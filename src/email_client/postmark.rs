@@ -0,0 +1,416 @@
+use super::{EmailHeader, EmailSender, SendEmailError};
+use crate::{domain::SubscriberEmail, telemetry};
+use axum::http::HeaderName;
+use reqwest::{Client, StatusCode};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Serialize;
+use std::time::Duration;
+use url::Url;
+
+const EMAIL_CLIENT_AUTH_HEADER: HeaderName = HeaderName::from_static("x-postmark-server-token");
+
+/// Sends emails through Postmark's JSON API.
+#[derive(Debug)]
+pub struct PostmarkClient {
+    http_client: Client,
+    sender_email: SubscriberEmail,
+    base_url: Url,
+    auth_token: SecretString,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    html_body: &'a str,
+    text_body: &'a str,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    headers: &'a [EmailHeader],
+}
+
+impl From<reqwest::Error> for SendEmailError {
+    fn from(err: reqwest::Error) -> Self {
+        let is_transient = match err.status() {
+            Some(status) => status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error(),
+            None => err.is_timeout() || err.is_connect(),
+        };
+
+        if is_transient {
+            Self::Transient(Box::new(err))
+        } else {
+            Self::Permanent(Box::new(err))
+        }
+    }
+}
+
+impl PostmarkClient {
+    pub fn new(
+        sender_email: SubscriberEmail,
+        base_url: Url,
+        auth_token: SecretString,
+        timeout: Duration,
+    ) -> Result<Self, reqwest::Error> {
+        let http_client = Client::builder().timeout(timeout).build()?;
+        Ok(Self {
+            http_client,
+            sender_email,
+            base_url,
+            auth_token,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailSender for PostmarkClient {
+    async fn send_email_with_headers(
+        &self,
+        recipeint: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[EmailHeader],
+    ) -> Result<(), SendEmailError> {
+        let request_body = SendEmailRequest {
+            from: self.sender_email.as_ref(),
+            to: recipeint.as_ref(),
+            subject,
+            html_body: html_content,
+            text_body: text_content,
+            headers,
+        };
+
+        self.http_client
+            .post(self.base_url.as_str())
+            .header(EMAIL_CLIENT_AUTH_HEADER, self.auth_token.expose_secret())
+            .headers(telemetry::trace_context_headers())
+            .json(&request_body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    async fn check_health(&self) -> Result<(), SendEmailError> {
+        // `/server` answers with the server settings only for a valid token
+        let url = self
+            .base_url
+            .join("server")
+            .map_err(|err| SendEmailError::Permanent(Box::new(err)))?;
+
+        self.http_client
+            .get(url)
+            .header(EMAIL_CLIENT_AUTH_HEADER, self.auth_token.expose_secret())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::SubscriberEmail;
+    use claims::{assert_err, assert_ok};
+    use fake::faker::internet::en::SafeEmail;
+    use fake::faker::lorem::en::{Paragraph, Sentence};
+    use fake::{Fake, Faker};
+    use reqwest::Method;
+    use reqwest::header::CONTENT_TYPE;
+    use secrecy::SecretString;
+    use std::str::FromStr;
+    use std::time::Duration;
+    use url::Url;
+    use wiremock::matchers::{any, body_partial_json, header, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    struct SendEmailBodyMatcher;
+
+    impl wiremock::Match for SendEmailBodyMatcher {
+        fn matches(&self, request: &wiremock::Request) -> bool {
+            let result: std::result::Result<serde_json::Value, _> =
+                serde_json::from_slice(&request.body);
+
+            if let Ok(body) = result {
+                // Check that all the mandatory fields are populated
+                // without inspecting the field values
+                body.get("From").is_some()
+                    && body.get("To").is_some()
+                    && body.get("Subject").is_some()
+                    && body.get("HtmlBody").is_some()
+                    && body.get("TextBody").is_some()
+            } else {
+                false
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_the_expected_request() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(&mock_server.uri());
+
+        Mock::given(header_exists(EMAIL_CLIENT_AUTH_HEADER))
+            .and(header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref()))
+            .and(method(Method::POST))
+            .and(SendEmailBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        _ = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        // Mock expectations are checked on drop
+    }
+
+    #[tokio::test]
+    async fn send_email_with_headers_sends_the_custom_headers() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(&mock_server.uri());
+
+        Mock::given(body_partial_json(serde_json::json!({
+            "Headers": [{ "Name": "List-Unsubscribe-Post", "Value": "List-Unsubscribe=One-Click" }]
+        })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+        // Act
+        let outcome = email_client
+            .send_email_with_headers(
+                &email(),
+                &subject(),
+                &content(),
+                &content(),
+                &[EmailHeader::new(
+                    "List-Unsubscribe-Post",
+                    "List-Unsubscribe=One-Click",
+                )],
+            )
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_succeeds_if_the_server_returns_200() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(&mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_returns_500() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(&mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_failure_is_retryable_if_the_server_returns_500() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(&mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert!(assert_err!(outcome).is_retryable());
+    }
+
+    #[tokio::test]
+    async fn send_email_failure_is_retryable_if_the_server_returns_429() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(&mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(429))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert!(assert_err!(outcome).is_retryable());
+    }
+
+    #[tokio::test]
+    async fn send_email_failure_is_permanent_if_the_server_returns_422() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(&mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(422))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert!(!assert_err!(outcome).is_retryable());
+    }
+
+    #[tokio::test]
+    async fn send_email_timeout_if_the_server_takes_to_long() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(&mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(60 * 3)))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert!(assert_err!(outcome).is_retryable());
+    }
+
+    #[tokio::test]
+    async fn send_email_failure_is_retryable_if_the_server_is_unreachable() {
+        // Arrange
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let email_client = email_client(&format!("http://{}", listener.local_addr().unwrap()));
+        drop(listener);
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert!(assert_err!(outcome).is_retryable());
+    }
+
+    #[tokio::test]
+    async fn check_health_fetches_the_server_with_the_auth_token() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(&mock_server.uri());
+
+        Mock::given(path("/server"))
+            .and(method(Method::GET))
+            .and(header_exists(EMAIL_CLIENT_AUTH_HEADER))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client.check_health().await;
+
+        // Assert
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn check_health_fails_if_the_token_is_rejected() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(&mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client.check_health().await;
+
+        // Assert
+        assert!(!assert_err!(outcome).is_retryable());
+    }
+
+    /// Generate a random email subject
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    /// Generate a random email content
+    fn content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    /// Generate a random subscriber email
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::try_from(SafeEmail().fake::<String>())
+            .expect("Expect to get valid subscriber email!")
+    }
+
+    /// Get a test instance of `PostmarkClient`.
+    fn email_client(base_url: &str) -> PostmarkClient {
+        PostmarkClient::new(
+            email(),
+            Url::from_str(base_url).expect("Expect to get valid email client base url"),
+            SecretString::new(Faker.fake::<String>().into()),
+            Duration::from_millis(200),
+        )
+        .expect("Expect email client to be initialized.")
+    }
+}
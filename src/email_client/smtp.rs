@@ -0,0 +1,309 @@
+use super::{EmailHeader, EmailSender, SendEmailError, build_message};
+use crate::{config::SmtpTls, domain::SubscriberEmail};
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Tokio1Executor,
+    transport::smtp::{self, authentication::Credentials},
+};
+use secrecy::{ExposeSecret, SecretString};
+use std::time::Duration;
+
+/// Sends emails to an SMTP relay.
+#[derive(Debug)]
+pub struct SmtpClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    sender_email: SubscriberEmail,
+}
+
+impl From<smtp::Error> for SendEmailError {
+    fn from(err: smtp::Error) -> Self {
+        // 5xx replies and messages the relay can't parse won't get better by
+        // retrying; network errors, timeouts and 4xx replies may.
+        if err.is_permanent() || err.is_client() {
+            Self::Permanent(Box::new(err))
+        } else {
+            Self::Transient(Box::new(err))
+        }
+    }
+}
+
+impl SmtpClient {
+    pub fn new(
+        sender_email: SubscriberEmail,
+        host: &str,
+        port: u16,
+        tls: SmtpTls,
+        credentials: Option<(String, SecretString)>,
+        timeout: Duration,
+    ) -> Result<Self, smtp::Error> {
+        let mut builder = match tls {
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+            SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?,
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host)?,
+        }
+        .port(port)
+        .timeout(Some(timeout));
+
+        if let Some((username, password)) = credentials {
+            builder = builder.credentials(Credentials::new(
+                username,
+                password.expose_secret().to_owned(),
+            ));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            sender_email,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailSender for SmtpClient {
+    async fn send_email_with_headers(
+        &self,
+        recipeint: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[EmailHeader],
+    ) -> Result<(), SendEmailError> {
+        let message = build_message(
+            &self.sender_email,
+            recipeint,
+            subject,
+            html_content,
+            text_content,
+            headers,
+        )?;
+
+        self.transport.send(message).await?;
+
+        Ok(())
+    }
+
+    async fn check_health(&self) -> Result<(), SendEmailError> {
+        if self.transport.test_connection().await? {
+            Ok(())
+        } else {
+            Err(SendEmailError::Transient(
+                "The SMTP relay did not answer NOOP".into(),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_err, assert_ok};
+    use fake::Fake;
+    use fake::faker::internet::en::SafeEmail;
+    use fake::faker::lorem::en::{Paragraph, Sentence};
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// A minimal SMTP server answering `RCPT TO` with `rcpt_reply` and
+    /// recording the data of every accepted message.
+    struct FakeSmtpServer {
+        port: u16,
+        messages: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl FakeSmtpServer {
+        async fn start(rcpt_reply: &'static str) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0")
+                .await
+                .expect("Expect to bind a random port");
+            let port = listener.local_addr().unwrap().port();
+            let messages = Arc::new(Mutex::new(Vec::new()));
+
+            let recorded = messages.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let recorded = recorded.clone();
+                    tokio::spawn(async move {
+                        let (reader, mut writer) = stream.into_split();
+                        let mut lines = BufReader::new(reader).lines();
+                        let mut data: Option<String> = None;
+
+                        writer.write_all(b"220 localhost ESMTP\r\n").await?;
+                        while let Some(line) = lines.next_line().await? {
+                            if let Some(message) = data.as_mut() {
+                                if line == "." {
+                                    recorded.lock().unwrap().push(data.take().unwrap());
+                                    writer.write_all(b"250 OK\r\n").await?;
+                                } else {
+                                    message.push_str(&line);
+                                    message.push('\n');
+                                }
+                                continue;
+                            }
+
+                            let command = line.to_ascii_uppercase();
+                            let reply = if command.starts_with("EHLO") {
+                                "250 localhost\r\n"
+                            } else if command.starts_with("RCPT") {
+                                rcpt_reply
+                            } else if command.starts_with("DATA") {
+                                data = Some(String::new());
+                                "354 End data with <CR><LF>.<CR><LF>\r\n"
+                            } else if command.starts_with("QUIT") {
+                                writer.write_all(b"221 Bye\r\n").await?;
+                                break;
+                            } else {
+                                "250 OK\r\n"
+                            };
+                            writer.write_all(reply.as_bytes()).await?;
+                        }
+
+                        std::io::Result::Ok(())
+                    });
+                }
+            });
+
+            Self { port, messages }
+        }
+
+        fn messages(&self) -> Vec<String> {
+            self.messages.lock().unwrap().clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn send_email_delivers_the_message_to_the_smtp_server() {
+        // Arrange
+        let server = FakeSmtpServer::start("250 OK\r\n").await;
+        let email_client = email_client(server.port);
+        let recipeint = email();
+
+        // Act
+        let outcome = email_client
+            .send_email_with_headers(
+                &recipeint,
+                "Newsletter title",
+                &content(),
+                &content(),
+                &[EmailHeader::new(
+                    "List-Unsubscribe-Post",
+                    "List-Unsubscribe=One-Click",
+                )],
+            )
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+        let messages = server.messages();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains(&format!("To: {}", recipeint.as_ref())));
+        assert!(messages[0].contains("Subject: Newsletter title"));
+        assert!(messages[0].contains("List-Unsubscribe-Post: List-Unsubscribe=One-Click"));
+    }
+
+    #[tokio::test]
+    async fn send_email_failure_is_permanent_if_the_recipient_is_rejected() {
+        // Arrange
+        let server = FakeSmtpServer::start("550 No such user\r\n").await;
+        let email_client = email_client(server.port);
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert!(!assert_err!(outcome).is_retryable());
+    }
+
+    #[tokio::test]
+    async fn send_email_failure_is_retryable_if_the_server_defers_the_recipient() {
+        // Arrange
+        let server = FakeSmtpServer::start("451 Try again later\r\n").await;
+        let email_client = email_client(server.port);
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert!(assert_err!(outcome).is_retryable());
+    }
+
+    #[tokio::test]
+    async fn send_email_failure_is_retryable_if_the_server_is_unreachable() {
+        // Arrange
+        let port = {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let email_client = email_client(port);
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert!(assert_err!(outcome).is_retryable());
+    }
+
+    #[tokio::test]
+    async fn check_health_succeeds_if_the_server_answers() {
+        // Arrange
+        let server = FakeSmtpServer::start("250 OK\r\n").await;
+        let email_client = email_client(server.port);
+
+        // Act
+        let outcome = email_client.check_health().await;
+
+        // Assert
+        assert_ok!(outcome);
+        assert!(server.messages().is_empty());
+    }
+
+    #[tokio::test]
+    async fn check_health_fails_if_the_server_is_unreachable() {
+        // Arrange
+        let port = {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let email_client = email_client(port);
+
+        // Act
+        let outcome = email_client.check_health().await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+
+    /// Generate a random email subject
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    /// Generate a random email content
+    fn content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    /// Generate a random subscriber email
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::try_from(SafeEmail().fake::<String>())
+            .expect("Expect to get valid subscriber email!")
+    }
+
+    /// Get a test instance of `SmtpClient` talking plain SMTP to localhost.
+    fn email_client(port: u16) -> SmtpClient {
+        SmtpClient::new(
+            email(),
+            "127.0.0.1",
+            port,
+            SmtpTls::None,
+            None,
+            Duration::from_secs(1),
+        )
+        .expect("Expect email client to be initialized.")
+    }
+}
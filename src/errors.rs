@@ -1,8 +1,22 @@
+use crate::startup::REQUEST_ID_HEADER;
 use axum::{
-    body::Body,
-    http::StatusCode,
+    Json,
+    extract::{
+        Request,
+        multipart::{MultipartError, MultipartRejection},
+        rejection::{FormRejection, JsonRejection},
+    },
+    http::{
+        HeaderValue, StatusCode,
+        header::{CONTENT_TYPE, WWW_AUTHENTICATE},
+    },
+    middleware::Next,
     response::{IntoResponse, Response},
 };
+use serde::Serialize;
+use utoipa::ToSchema;
+
+const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
 
 pub type Result<T = ()> = std::result::Result<T, Error>;
 
@@ -25,9 +39,23 @@ pub enum Error {
     #[error(transparent)]
     Reqwest(#[from] reqwest::Error),
     #[error(transparent)]
+    Email(#[from] crate::email_client::SendEmailError),
+    #[error(transparent)]
+    Smtp(#[from] lettre::transport::smtp::Error),
+    #[error(transparent)]
     Session(#[from] tower_sessions::session::Error),
+    #[error(transparent)]
+    Telemetry(#[from] opentelemetry_otlp::ExporterBuildError),
     #[error("{0:?}")]
     Auth(String),
+    /// The request can't be served as asked, e.g. it refers to an unknown
+    /// token. `detail` is sent to the client.
+    #[error("{code}: {detail}")]
+    Rejected {
+        status: StatusCode,
+        code: &'static str,
+        detail: &'static str,
+    },
 
     #[error("{0:?}")]
     Custom(String),
@@ -39,25 +67,189 @@ impl From<surrealdb::Error> for Error {
     }
 }
 
+impl From<JsonRejection> for Error {
+    fn from(rejection: JsonRejection) -> Self {
+        invalid_body(rejection.body_text())
+    }
+}
+
+impl From<FormRejection> for Error {
+    fn from(rejection: FormRejection) -> Self {
+        invalid_body(rejection.body_text())
+    }
+}
+
+impl From<MultipartRejection> for Error {
+    fn from(rejection: MultipartRejection) -> Self {
+        invalid_body(rejection.body_text())
+    }
+}
+
+impl From<MultipartError> for Error {
+    fn from(err: MultipartError) -> Self {
+        invalid_body(err.body_text())
+    }
+}
+
+fn invalid_body(message: String) -> Error {
+    validator::ValidationError::new("INVALID_BODY")
+        .with_message(message.into())
+        .into()
+}
+
+impl Error {
+    /// Machine-readable code of the error, stable across releases.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::SurrealDb(_) => "DATABASE_ERROR",
+            Self::Migrations(_) => "MIGRATION_ERROR",
+            Self::Io(_) => "IO_ERROR",
+            Self::Config(_) => "CONFIG_ERROR",
+            Self::UrlParse(_) => "URL_PARSE_ERROR",
+            Self::ValidationErrors(_) | Self::ValidationError(_) => "VALIDATION_FAILED",
+            Self::Reqwest(_) => "HTTP_CLIENT_ERROR",
+            Self::Email(_) => "EMAIL_DELIVERY_FAILED",
+            Self::Smtp(_) => "SMTP_ERROR",
+            Self::Session(_) => "SESSION_ERROR",
+            Self::Telemetry(_) => "TELEMETRY_ERROR",
+            Self::Auth(_) => "UNAUTHORIZED",
+            Self::Rejected { code, .. } => code,
+            Self::Custom(_) => "INTERNAL_ERROR",
+        }
+    }
+}
+
+tokio::task_local! {
+    /// Id of the request being handled, set by [`scope_request_id`].
+    static REQUEST_ID: Option<String>;
+}
+
+/// Make the `x-request-id` of the request available to the error responses
+/// built while handling it.
+pub async fn scope_request_id(request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|request_id| request_id.to_str().ok())
+        .map(str::to_owned);
+
+    REQUEST_ID.scope(request_id, next.run(request)).await
+}
+
+/// An RFC 7807 problem details body.
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct Problem {
+    #[schema(example = "about:blank")]
+    r#type: &'static str,
+    #[schema(example = "Bad Request")]
+    title: &'static str,
+    #[schema(example = 400)]
+    status: u16,
+    #[schema(example = "VALIDATION_FAILED")]
+    code: &'static str,
+    detail: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct FieldError {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "email")]
+    field: Option<String>,
+    #[schema(example = "INVALID_SUBSCRIBER_EMAIL")]
+    code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+impl FieldError {
+    fn new(field: Option<&str>, error: &validator::ValidationError) -> Self {
+        Self {
+            field: field.map(str::to_owned),
+            code: error.code.to_string(),
+            message: error.message.as_ref().map(|message| message.to_string()),
+        }
+    }
+}
+
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
-        match self {
-            Self::ValidationErrors(_) | Self::ValidationError(_) => {
+        let (status, detail, errors) = match &self {
+            Self::ValidationErrors(errors) => {
                 tracing::warn!("Bad request: - {self:?}");
-                StatusCode::BAD_REQUEST.into_response()
+                let mut fields = errors.field_errors().into_iter().collect::<Vec<_>>();
+                fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+                let errors = fields
+                    .into_iter()
+                    .flat_map(|(field, errors)| {
+                        errors
+                            .iter()
+                            .map(move |error| FieldError::new(Some(&field), error))
+                    })
+                    .collect();
+                (StatusCode::BAD_REQUEST, "The request is invalid", errors)
+            }
+            Self::ValidationError(error) => {
+                tracing::warn!("Bad request: - {self:?}");
+                (
+                    StatusCode::BAD_REQUEST,
+                    "The request is invalid",
+                    vec![FieldError::new(None, error)],
+                )
             }
             Self::Auth(_) => {
                 tracing::warn!("Unauthorized : - {self:?}");
-                Response::builder()
-                    .status(StatusCode::UNAUTHORIZED)
-                    .header("WWW-Authenticate", r#"Basic realm="publish""#)
-                    .body(Body::empty())
-                    .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+                (
+                    StatusCode::UNAUTHORIZED,
+                    "Authentication failed",
+                    Vec::new(),
+                )
+            }
+            Self::Rejected { status, detail, .. } => {
+                tracing::warn!("Rejected request: - {self:?}");
+                (*status, *detail, Vec::new())
             }
             _ => {
+                // The cause stays in the logs, it may expose internals
                 tracing::error!("Internal Server: - {self:?}");
-                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "An unexpected error occurred",
+                    Vec::new(),
+                )
             }
+        };
+
+        let problem = Problem {
+            r#type: "about:blank",
+            title: status.canonical_reason().unwrap_or_default(),
+            status: status.as_u16(),
+            code: self.code(),
+            detail: detail.into(),
+            errors,
+            request_id: REQUEST_ID.try_with(Clone::clone).ok().flatten(),
+        };
+
+        let mut response = (
+            status,
+            [(CONTENT_TYPE, PROBLEM_CONTENT_TYPE)],
+            Json(problem),
+        )
+            .into_response();
+        if let Self::Auth(_) = self {
+            let headers = response.headers_mut();
+            headers.insert(
+                WWW_AUTHENTICATE,
+                HeaderValue::from_static(r#"Basic realm="publish""#),
+            );
+            headers.append(
+                WWW_AUTHENTICATE,
+                HeaderValue::from_static(r#"Bearer realm="publish""#),
+            );
         }
+        response
     }
 }
@@ -0,0 +1,220 @@
+use crate::{
+    Result,
+    authentication::AuthenticatedUser,
+    domain::{ApiScope, NewApiToken, generate_api_token},
+    model::ModelManager,
+};
+use axum::{
+    Form,
+    extract::{Path, State},
+    http::header::CACHE_CONTROL,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use axum_messages::Messages;
+use htmlescape::encode_minimal;
+use reqwest::StatusCode;
+use std::sync::Arc;
+use surrealdb::RecordId;
+
+const API_TOKENS_TABLE: &str = "api_tokens";
+
+pub async fn admin_api_tokens(
+    State(mm): State<Arc<ModelManager>>,
+    messages: Messages,
+) -> Result<impl IntoResponse> {
+    let flash_messages = messages
+        .into_iter()
+        .map(|message| format!("<p><i>{}</i></p>", message.message))
+        .collect::<Vec<_>>()
+        .join("");
+
+    let rows = mm
+        .list_api_tokens()
+        .await?
+        .into_iter()
+        .map(|token| {
+            let status = if token.revoked {
+                "Revoked"
+            } else if token.expired {
+                "Expired"
+            } else {
+                "Active"
+            };
+            let revoke = if token.revoked {
+                String::new()
+            } else {
+                format!(
+                    r#"
+                    <form action="/admin/api-tokens/{key}/revoke" method="post">
+                        <button type="submit">Revoke</button>
+                    </form>
+                    "#,
+                    key = token.id.key(),
+                )
+            };
+            format!(
+                r#"
+                <tr>
+                    <td>{name}</td>
+                    <td>{owner}</td>
+                    <td>{scopes}</td>
+                    <td>{created_at}</td>
+                    <td>{expires_at}</td>
+                    <td>{last_used_at}</td>
+                    <td>{status}</td>
+                    <td>{revoke}</td>
+                </tr>
+                "#,
+                name = encode_minimal(&token.name),
+                owner = encode_minimal(&token.owner),
+                scopes = token
+                    .scopes
+                    .iter()
+                    .map(ApiScope::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                created_at = token.created_at,
+                expires_at = token.expires_at.as_deref().unwrap_or("Never"),
+                last_used_at = token.last_used_at.as_deref().unwrap_or("Never"),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("");
+
+    let scope_options = ApiScope::ALL
+        .iter()
+        .map(|scope| {
+            format!(
+                r#"<label><input type="checkbox" name="scopes" value="{scope}"> {scope}</label><br>"#,
+                scope = scope.as_str()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("");
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>API tokens</title>
+        </head>
+        <body>
+            {flash_messages}
+            <form action="/admin/api-tokens" method="post">
+                <label>Name
+                    <input type="text" placeholder="What will use the token" name="name">
+                </label>
+                <br>
+                {scope_options}
+                <label>Expires in (days)
+                    <input type="number" min="1" placeholder="Never" name="expires_in_days">
+                </label>
+                <br>
+                <button type="submit">Create token</button>
+            </form>
+            <table>
+                <thead>
+                    <tr>
+                        <th>Name</th>
+                        <th>Owner</th>
+                        <th>Scopes</th>
+                        <th>Created at</th>
+                        <th>Expires at</th>
+                        <th>Last used at</th>
+                        <th>Status</th>
+                        <th></th>
+                    </tr>
+                </thead>
+                <tbody>
+                    {rows}
+                </tbody>
+            </table>
+            <p><a href="/admin/dashboard">&lt;- Back</a></p>
+        </body>
+        </html>
+        "#
+    );
+
+    Ok((StatusCode::OK, Html(body)).into_response())
+}
+
+/// Issue a token and show it, the only time it can be read.
+pub async fn admin_create_api_token(
+    State(mm): State<Arc<ModelManager>>,
+    messages: Messages,
+    user: AuthenticatedUser,
+    // The scopes checkboxes share a name, which structs can't collect
+    Form(fields): Form<Vec<(String, String)>>,
+) -> Result<Response> {
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map_or("", |(_, value)| value.as_str())
+    };
+    let scopes = fields
+        .iter()
+        .filter(|(field, _)| field == "scopes")
+        .map(|(_, scope)| scope.clone())
+        .collect::<Vec<_>>();
+
+    let token = match NewApiToken::parse(field("name"), &scopes, field("expires_in_days")) {
+        Ok(token) => token,
+        Err(err) => {
+            messages.error(
+                err.message
+                    .unwrap_or_else(|| "The token is invalid.".into()),
+            );
+            return Ok(Redirect::to("/admin/api-tokens").into_response());
+        }
+    };
+
+    let secret = generate_api_token();
+    mm.create_api_token(&user.id, &token, &secret).await?;
+    tracing::info!("{} created the API token {:?}", user.username, token.name);
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>API token created</title>
+        </head>
+        <body>
+            <p>The API token <b>{name}</b> has been created:</p>
+            <p><code id="api-token">{secret}</code></p>
+            <p>Copy it now, it won't be shown again.</p>
+            <p><a href="/admin/api-tokens">&lt;- Back</a></p>
+        </body>
+        </html>
+        "#,
+        name = encode_minimal(&token.name),
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        [(CACHE_CONTROL, "no-store")],
+        Html(body),
+    )
+        .into_response())
+}
+
+pub async fn admin_revoke_api_token(
+    State(mm): State<Arc<ModelManager>>,
+    messages: Messages,
+    Path(key): Path<String>,
+) -> Result<impl IntoResponse> {
+    if mm
+        .revoke_api_token(RecordId::from_table_key(API_TOKENS_TABLE, key))
+        .await?
+    {
+        messages.info("The API token has been revoked");
+    } else {
+        messages.warning("The API token was not found or is already revoked");
+    }
+
+    Ok(Redirect::to("/admin/api-tokens"))
+}
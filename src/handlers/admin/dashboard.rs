@@ -0,0 +1,49 @@
+use crate::{Result, authentication::AuthenticatedUser};
+use axum::response::{Html, IntoResponse};
+use axum_messages::Messages;
+use htmlescape::encode_minimal;
+use reqwest::StatusCode;
+
+pub async fn admin_dashboard(
+    messages: Messages,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse> {
+    let flash_messages = messages
+        .into_iter()
+        .map(|message| format!("<p><i>{}</i></p>", message.message))
+        .collect::<Vec<_>>()
+        .join("");
+
+    let username = encode_minimal(&user.username);
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html>
+            <head>
+                <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                <title>Admin dashboard</title>
+            </head>
+            <body>
+                {flash_messages}
+                <p>Welcome {username}</p>
+                <p>Available actions:</p>
+                <ol>
+                    <li><a href="/admin/newsletters">Send a newsletter issue</a></li>
+                    <li><a href="/admin/subscribers">Subscribers</a></li>
+                    <li><a href="/admin/deliveries/failed">Failed deliveries</a></li>
+                    <li><a href="/admin/api-tokens">API tokens</a></li>
+                    <li><a href="/admin/password">Change password</a></li>
+                    <li><a href="/admin/two-factor">Two-factor authentication</a></li>
+                    <li>
+                        <form name="logoutForm" action="/admin/logout" method="post">
+                            <input type="submit" value="Logout">
+                        </form>
+                    </li>
+                </ol>
+            </body>
+        </html>
+        "#
+    );
+
+    Ok((StatusCode::OK, Html(body)))
+}
@@ -0,0 +1,104 @@
+use crate::{Result, model::ModelManager};
+use axum::{
+    extract::{Path, State},
+    response::{Html, IntoResponse, Redirect},
+};
+use axum_messages::Messages;
+use htmlescape::encode_minimal;
+use reqwest::StatusCode;
+use std::sync::Arc;
+use surrealdb::RecordId;
+
+const DEAD_LETTERS_TABLE: &str = "issue_delivery_dead_letters";
+
+pub async fn admin_dead_letters(
+    State(mm): State<Arc<ModelManager>>,
+    messages: Messages,
+) -> Result<impl IntoResponse> {
+    let flash_messages = messages
+        .into_iter()
+        .map(|message| format!("<p><i>{}</i></p>", message.message))
+        .collect::<Vec<_>>()
+        .join("");
+
+    let rows = mm
+        .get_dead_letters()
+        .await?
+        .into_iter()
+        .map(|letter| {
+            format!(
+                r#"
+                <tr>
+                    <td>{email}</td>
+                    <td>{title}</td>
+                    <td>{attempts}</td>
+                    <td>{last_error}</td>
+                    <td>{failed_at}</td>
+                    <td>
+                        <form action="/admin/deliveries/failed/{key}/replay" method="post">
+                            <button type="submit">Replay</button>
+                        </form>
+                    </td>
+                </tr>
+                "#,
+                email = encode_minimal(&letter.subscriber_email),
+                title = encode_minimal(&letter.title),
+                attempts = letter.attempts,
+                last_error = encode_minimal(&letter.last_error),
+                failed_at = letter.failed_at,
+                key = letter.id.key(),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("");
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Failed deliveries</title>
+        </head>
+        <body>
+            {flash_messages}
+            <table>
+                <thead>
+                    <tr>
+                        <th>Subscriber</th>
+                        <th>Issue</th>
+                        <th>Attempts</th>
+                        <th>Last error</th>
+                        <th>Failed at</th>
+                        <th></th>
+                    </tr>
+                </thead>
+                <tbody>
+                    {rows}
+                </tbody>
+            </table>
+            <p><a href="/admin/dashboard">&lt;- Back</a></p>
+        </body>
+        </html>
+        "#
+    );
+
+    Ok((StatusCode::OK, Html(body)).into_response())
+}
+
+pub async fn replay_dead_letter(
+    State(mm): State<Arc<ModelManager>>,
+    messages: Messages,
+    Path(key): Path<String>,
+) -> Result<impl IntoResponse> {
+    if mm
+        .replay_dead_letter(RecordId::from_table_key(DEAD_LETTERS_TABLE, key))
+        .await?
+    {
+        messages.info("The delivery has been queued again");
+    } else {
+        messages.warning("The delivery was not found");
+    }
+
+    Ok(Redirect::to("/admin/deliveries/failed").into_response())
+}
@@ -0,0 +1,9 @@
+use crate::{Result, session_state::TypedSession};
+use axum::response::{IntoResponse, Redirect};
+use axum_messages::Messages;
+
+pub async fn admin_logout(messages: Messages, session: TypedSession) -> Result<impl IntoResponse> {
+    session.log_out().await?;
+    messages.info("You have successfully logged out.");
+    Ok(Redirect::to("/login"))
+}
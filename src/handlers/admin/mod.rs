@@ -1,37 +1,19 @@
-use crate::{Result, model::ModelManager, session_state::TypedSession};
-use axum::{
-    extract::State,
-    response::{Html, IntoResponse, Redirect},
-};
-use reqwest::StatusCode;
-use std::sync::Arc;
+mod api_tokens;
+mod dashboard;
+mod dead_letters;
+mod logout;
+mod newsletters;
+mod password;
+mod subscribers;
+mod subscribers_csv;
+mod two_factor;
 
-pub async fn admin_dashboard(
-    State(mm): State<Arc<ModelManager>>,
-    session: TypedSession,
-) -> Result<impl IntoResponse> {
-    let username = match session.get_user_id().await {
-        Ok(Some(user_id)) => mm.get_username(user_id).await?,
-        reason => {
-            tracing::error!("Failed to authenticate: {reason:?}");
-            return Ok(Redirect::to("/login").into_response());
-        }
-    };
-
-    let body = format!(
-        r#"
-        <!DOCTYPE html>
-        <html>
-            <head>
-                <meta http-equiv="content-type" content="text/html; charset=utf-8">
-                <title>Admin dashboard</title>
-            </head>
-            <body>
-                <p>Welcome {username}</p>
-            </body>
-        </html>
-        "#
-    );
-
-    Ok((StatusCode::OK, Html(body)).into_response())
-}
+pub use api_tokens::*;
+pub use dashboard::*;
+pub use dead_letters::*;
+pub use logout::*;
+pub use newsletters::*;
+pub use password::*;
+pub use subscribers::*;
+pub use subscribers_csv::*;
+pub use two_factor::*;
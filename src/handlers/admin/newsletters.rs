@@ -0,0 +1,118 @@
+use crate::{
+    Config, Result,
+    authentication::AuthenticatedUser,
+    domain::IdempotencyKey,
+    handlers::{BodyData, get_random_token, newsletter::publish_idempotently},
+    model::{IdempotencyEndpoint, ModelManager},
+};
+use axum::{
+    Form,
+    extract::State,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use axum_messages::Messages;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::sync::Arc;
+
+#[derive(Debug, Deserialize)]
+pub struct NewsletterForm {
+    title: String,
+    html_content: String,
+    text_content: String,
+    idempotency_key: String,
+}
+
+pub async fn admin_newsletter_form(messages: Messages) -> Result<impl IntoResponse> {
+    let flash_messages = messages
+        .into_iter()
+        .map(|message| format!("<p><i>{}</i></p>", message.message))
+        .collect::<Vec<_>>()
+        .join("");
+    // A fresh key per rendered form, so resubmitting it publishes only once
+    let idempotency_key = get_random_token();
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Send a newsletter issue</title>
+        </head>
+        <body>
+            {flash_messages}
+            <form action="/admin/newsletters" method="post">
+                <label>Title
+                    <input
+                        type="text"
+                        placeholder="Enter the issue title"
+                        name="title"
+                    >
+                </label>
+                <br>
+                <label>HTML content
+                    <textarea
+                        placeholder="Enter the content as HTML"
+                        name="html_content"
+                        rows="20"
+                        cols="50"
+                    ></textarea>
+                </label>
+                <br>
+                <label>Text content
+                    <textarea
+                        placeholder="Enter the content as plain text"
+                        name="text_content"
+                        rows="20"
+                        cols="50"
+                    ></textarea>
+                </label>
+                <br>
+                <input hidden type="text" name="idempotency_key" value="{idempotency_key}">
+                <button type="submit">Send</button>
+            </form>
+            <p><a href="/admin/dashboard">&lt;- Back</a></p>
+        </body>
+        </html>
+        "#
+    );
+
+    Ok((StatusCode::OK, Html(body)).into_response())
+}
+
+#[tracing::instrument(skip(mm, config, user, messages, form), fields(title = %form.title))]
+pub async fn admin_publish_newsletter(
+    State(mm): State<Arc<ModelManager>>,
+    State(config): State<Arc<Config>>,
+    user: AuthenticatedUser,
+    messages: Messages,
+    Form(form): Form<NewsletterForm>,
+) -> Result<Response> {
+    if [&form.title, &form.html_content, &form.text_content]
+        .iter()
+        .any(|field| field.trim().is_empty())
+    {
+        messages.warning("The title, HTML and text content are all required");
+        return Ok(Redirect::to("/admin/newsletters").into_response());
+    }
+
+    let idempotency_key = IdempotencyKey::try_from(form.idempotency_key)?;
+    let body = BodyData::new(form.title, form.html_content, form.text_content);
+
+    publish_idempotently(
+        &mm,
+        &config.idempotency,
+        &user.id,
+        IdempotencyEndpoint::AdminNewsletters,
+        Some(idempotency_key),
+        &body,
+        |queued| {
+            messages.info(format!(
+                "The newsletter issue has been queued for {queued} subscribers"
+            ));
+            Redirect::to("/admin/dashboard").into_response()
+        },
+    )
+    .await
+}
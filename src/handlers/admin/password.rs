@@ -0,0 +1,111 @@
+use crate::{
+    Result, authentication::AuthenticatedUser, domain::NewPassword, handlers::Credentials,
+    model::ModelManager,
+};
+use axum::{
+    Form,
+    extract::State,
+    response::{Html, IntoResponse, Redirect},
+};
+use axum_messages::Messages;
+use reqwest::StatusCode;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use std::sync::Arc;
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordForm {
+    current_password: SecretString,
+    new_password: SecretString,
+    new_password_check: SecretString,
+}
+
+pub async fn admin_password_form(messages: Messages) -> Result<impl IntoResponse> {
+    let flash_messages = messages
+        .into_iter()
+        .map(|message| format!("<p><i>{}</i></p>", message.message))
+        .collect::<Vec<_>>()
+        .join("");
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Change password</title>
+        </head>
+        <body>
+            {flash_messages}
+            <form action="/admin/password" method="post">
+                <label>Current password
+                    <input
+                        type="password"
+                        placeholder="Enter current password"
+                        name="current_password"
+                    >
+                </label>
+                <br>
+                <label>New password
+                    <input
+                        type="password"
+                        placeholder="Enter new password"
+                        name="new_password"
+                    >
+                </label>
+                <br>
+                <label>Confirm new password
+                    <input
+                        type="password"
+                        placeholder="Type the new password again"
+                        name="new_password_check"
+                    >
+                </label>
+                <br>
+                <button type="submit">Change password</button>
+            </form>
+            <p><a href="/admin/dashboard">&lt;- Back</a></p>
+        </body>
+        </html>
+        "#
+    );
+
+    Ok((StatusCode::OK, Html(body)).into_response())
+}
+
+pub async fn admin_change_password(
+    State(mm): State<Arc<ModelManager>>,
+    messages: Messages,
+    user: AuthenticatedUser,
+    Form(form): Form<ChangePasswordForm>,
+) -> Result<impl IntoResponse> {
+    if form.new_password.expose_secret() != form.new_password_check.expose_secret() {
+        messages.error("You entered two different new passwords - the field values must match.");
+        return Ok(Redirect::to("/admin/password"));
+    }
+
+    let credentials = Credentials {
+        username: user.username,
+        password: form.current_password,
+    };
+    if let Err(err) = mm.validate_credientials(credentials).await {
+        tracing::warn!("Failed to validate the current password: {err:?}");
+        messages.error("The current password is incorrect.");
+        return Ok(Redirect::to("/admin/password"));
+    }
+
+    let new_password = match NewPassword::try_from(form.new_password) {
+        Ok(password) => password,
+        Err(err) => {
+            messages.error(
+                err.message
+                    .unwrap_or_else(|| "The new password is too weak.".into()),
+            );
+            return Ok(Redirect::to("/admin/password"));
+        }
+    };
+
+    mm.change_password(user.id, &new_password).await?;
+    messages.info("Your password has been changed.");
+    Ok(Redirect::to("/admin/password"))
+}
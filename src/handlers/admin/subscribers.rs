@@ -0,0 +1,345 @@
+use crate::{
+    Result,
+    model::{ModelManager, SortOrder, SubscriberCursor, SubscriberQuery, SubscriptionStatus},
+};
+use axum::{
+    extract::{Path, Query, State},
+    response::{Html, IntoResponse},
+};
+use htmlescape::encode_minimal;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::sync::Arc;
+use surrealdb::RecordId;
+use validator::ValidationError;
+
+const SUBSCRIPTIONS_TABLE: &str = "subscriptions";
+const PAGE_SIZE: usize = 20;
+
+#[derive(Debug, Deserialize)]
+pub struct SubscribersParams {
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    q: String,
+    #[serde(default)]
+    sort: String,
+    cursor: Option<String>,
+}
+
+pub async fn admin_subscribers(
+    State(mm): State<Arc<ModelManager>>,
+    Query(params): Query<SubscribersParams>,
+) -> Result<impl IntoResponse> {
+    let status = parse_status(&params.status)?;
+    let sort = match params.sort.as_str() {
+        "" | "newest" => SortOrder::NewestFirst,
+        "oldest" => SortOrder::OldestFirst,
+        _ => {
+            return Err(ValidationError::new("INVALID_SORT")
+                .with_message("sort must be either `newest` or `oldest`".into())
+                .into());
+        }
+    };
+    let after = params.cursor.as_deref().map(decode_cursor).transpose()?;
+    let search = params.q.trim();
+
+    let page = mm
+        .list_subscribers(&SubscriberQuery {
+            status,
+            search: (!search.is_empty()).then(|| search.to_string()),
+            sort,
+            after,
+            limit: PAGE_SIZE,
+        })
+        .await?;
+
+    let rows = page
+        .subscribers
+        .iter()
+        .map(|subscriber| {
+            format!(
+                r#"
+                <tr>
+                    <td><a href="/admin/subscribers/{key}">{email}</a></td>
+                    <td>{name}</td>
+                    <td>{status}</td>
+                    <td>{created_at}</td>
+                </tr>
+                "#,
+                key = subscriber.id.key(),
+                email = encode_minimal(&subscriber.email),
+                name = encode_minimal(&subscriber.name),
+                status = subscriber.status.as_str(),
+                created_at = subscriber.created_at,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("");
+
+    let next_page = page
+        .next
+        .map(|cursor| {
+            format!(
+                r#"<p><a href="/admin/subscribers?status={status}&q={q}&sort={sort}&cursor={cursor}">Next page -&gt;</a></p>"#,
+                status = urlencoding::encode(&params.status),
+                q = urlencoding::encode(search),
+                sort = urlencoding::encode(&params.sort),
+                cursor = urlencoding::encode(&encode_cursor(&cursor)),
+            )
+        })
+        .unwrap_or_default();
+
+    let status_options = [
+        ("", "All"),
+        ("PENDING", "Pending"),
+        ("CONFIRMED", "Confirmed"),
+        ("UNSUBSCRIBED", "Unsubscribed"),
+    ]
+    .iter()
+    .map(|(value, label)| {
+        let selected = if *value == params.status {
+            " selected"
+        } else {
+            ""
+        };
+        format!(r#"<option value="{value}"{selected}>{label}</option>"#)
+    })
+    .collect::<Vec<_>>()
+    .join("");
+    let oldest_selected = if sort == SortOrder::OldestFirst {
+        " selected"
+    } else {
+        ""
+    };
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Subscribers</title>
+        </head>
+        <body>
+            <form action="/admin/subscribers" method="get">
+                <label>Search
+                    <input
+                        type="text"
+                        placeholder="Email or name prefix"
+                        name="q"
+                        value="{search}"
+                    >
+                </label>
+                <label>Status
+                    <select name="status">{status_options}</select>
+                </label>
+                <label>Sort
+                    <select name="sort">
+                        <option value="newest">Newest first</option>
+                        <option value="oldest"{oldest_selected}>Oldest first</option>
+                    </select>
+                </label>
+                <button type="submit">Filter</button>
+            </form>
+            <table>
+                <thead>
+                    <tr>
+                        <th>Email</th>
+                        <th>Name</th>
+                        <th>Status</th>
+                        <th>Subscribed at</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    {rows}
+                </tbody>
+            </table>
+            {next_page}
+            <p>
+                <a href="/admin/subscribers/export.csv">Export as CSV</a>
+                | <a href="/admin/subscribers/import">Import from CSV</a>
+            </p>
+            <p><a href="/admin/dashboard">&lt;- Back</a></p>
+        </body>
+        </html>
+        "#,
+        search = encode_minimal(search),
+    );
+
+    Ok((StatusCode::OK, Html(body)))
+}
+
+pub async fn admin_subscriber(
+    State(mm): State<Arc<ModelManager>>,
+    Path(key): Path<String>,
+) -> Result<impl IntoResponse> {
+    let id = RecordId::from_table_key(SUBSCRIPTIONS_TABLE, key);
+    let Some(subscriber) = mm.get_subscriber(id.clone()).await? else {
+        return Ok((StatusCode::NOT_FOUND, Html(not_found_page())));
+    };
+
+    let tokens = mm
+        .get_subscriber_tokens(id)
+        .await?
+        .into_iter()
+        .map(|token| {
+            format!(
+                "<tr><td>{}</td><td>{}</td></tr>",
+                token.created_at,
+                if token.current { "Current" } else { "Replaced" },
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("");
+
+    let deliveries = mm
+        .get_subscriber_deliveries(&subscriber.email)
+        .await?
+        .into_iter()
+        .map(|delivery| {
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                encode_minimal(&delivery.title),
+                delivery.outcome,
+                encode_minimal(delivery.error.as_deref().unwrap_or_default()),
+                delivery.attempted_at,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("");
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Subscriber</title>
+        </head>
+        <body>
+            <p>Email: {email}</p>
+            <p>Name: {name}</p>
+            <p>Status: {status}</p>
+            <p>Subscribed at: {created_at}</p>
+            <h2>Confirmation tokens</h2>
+            <table>
+                <thead>
+                    <tr>
+                        <th>Issued at</th>
+                        <th>State</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    {tokens}
+                </tbody>
+            </table>
+            <h2>Deliveries</h2>
+            <table>
+                <thead>
+                    <tr>
+                        <th>Issue</th>
+                        <th>Outcome</th>
+                        <th>Error</th>
+                        <th>Attempted at</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    {deliveries}
+                </tbody>
+            </table>
+            <p><a href="/admin/subscribers">&lt;- Back</a></p>
+        </body>
+        </html>
+        "#,
+        email = encode_minimal(&subscriber.email),
+        name = encode_minimal(&subscriber.name),
+        status = subscriber.status.as_str(),
+        created_at = subscriber.created_at,
+    );
+
+    Ok((StatusCode::OK, Html(body)))
+}
+
+fn not_found_page() -> String {
+    r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Subscriber not found</title>
+        </head>
+        <body>
+            <p>This subscriber does not exist.</p>
+            <p><a href="/admin/subscribers">&lt;- Back</a></p>
+        </body>
+        </html>
+        "#
+    .to_string()
+}
+
+fn parse_status(status: &str) -> Result<Option<SubscriptionStatus>> {
+    Ok(match status {
+        "" => None,
+        "PENDING" => Some(SubscriptionStatus::Pending),
+        "CONFIRMED" => Some(SubscriptionStatus::Confirmed),
+        "UNSUBSCRIBED" => Some(SubscriptionStatus::Unsubscribed),
+        _ => {
+            return Err(ValidationError::new("INVALID_STATUS")
+                .with_message("status must be PENDING, CONFIRMED or UNSUBSCRIBED".into())
+                .into());
+        }
+    })
+}
+
+fn encode_cursor(cursor: &SubscriberCursor) -> String {
+    format!("{}|{}", cursor.created_at, cursor.id.key())
+}
+
+fn decode_cursor(cursor: &str) -> Result<SubscriberCursor> {
+    let invalid_cursor =
+        || ValidationError::new("INVALID_CURSOR").with_message("cursor is malformed".into());
+
+    let (created_at, key) = cursor.split_once('|').ok_or_else(invalid_cursor)?;
+    let is_datetime = !created_at.is_empty()
+        && created_at
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '-' | ':' | '.' | 'T' | 'Z' | '+'));
+    let is_key = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric());
+    if !is_datetime || !is_key {
+        return Err(invalid_cursor().into());
+    }
+
+    Ok(SubscriberCursor {
+        created_at: created_at.to_string(),
+        id: RecordId::from_table_key(SUBSCRIPTIONS_TABLE, key),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::assert_err;
+
+    #[test]
+    fn cursors_roundtrip() {
+        let cursor = SubscriberCursor {
+            created_at: "2026-10-18T12:56:26.424403Z".to_string(),
+            id: RecordId::from_table_key(SUBSCRIPTIONS_TABLE, "k4sa9ztx4mvb5c0fbs2v"),
+        };
+
+        let decoded = decode_cursor(&encode_cursor(&cursor)).expect("Expect a valid cursor");
+
+        assert_eq!(decoded, cursor);
+    }
+
+    #[test]
+    fn malformed_cursors_are_rejected() {
+        assert_err!(decode_cursor(""));
+        assert_err!(decode_cursor("2026-10-18T12:56:26Z"));
+        assert_err!(decode_cursor("|k4sa9ztx4mvb5c0fbs2v"));
+        assert_err!(decode_cursor("2026-10-18T12:56:26Z|"));
+        assert_err!(decode_cursor("2026-10-18T12:56:26Z|key; DELETE users"));
+        assert_err!(decode_cursor("time::now()|k4sa9ztx4mvb5c0fbs2v"));
+    }
+}
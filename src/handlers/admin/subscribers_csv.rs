@@ -0,0 +1,378 @@
+use crate::{
+    Error, Result,
+    authentication::AuthenticatedUser,
+    domain::Subscriber,
+    handlers::{FormData, get_random_token},
+    model::{ModelManager, SortOrder, SubscriberCursor, SubscriberQuery, SubscriptionStatus},
+};
+use axum::{
+    body::Body,
+    extract::{Multipart, State},
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use axum_messages::Messages;
+use futures_util::stream;
+use htmlescape::encode_minimal;
+use reqwest::StatusCode;
+use std::{borrow::Cow, sync::Arc};
+use validator::ValidationError;
+
+/// Number of subscribers loaded from the database per exported chunk.
+const EXPORT_BATCH_SIZE: usize = 500;
+
+/// Largest accepted upload, around a hundred thousand contacts.
+pub const IMPORT_BODY_LIMIT: usize = 5 * 1024 * 1024;
+
+/// Streams every subscriber as CSV, oldest first, one batch at a time.
+pub async fn export_subscribers(State(mm): State<Arc<ModelManager>>) -> Result<impl IntoResponse> {
+    struct ExportState {
+        mm: Arc<ModelManager>,
+        after: Option<SubscriberCursor>,
+        is_first: bool,
+        is_done: bool,
+    }
+
+    let batches = stream::try_unfold(
+        ExportState {
+            mm,
+            after: None,
+            is_first: true,
+            is_done: false,
+        },
+        async |state| {
+            if state.is_done {
+                return Ok(None);
+            }
+
+            let page = state
+                .mm
+                .list_subscribers(&SubscriberQuery {
+                    status: None,
+                    search: None,
+                    sort: SortOrder::OldestFirst,
+                    after: state.after.clone(),
+                    limit: EXPORT_BATCH_SIZE,
+                })
+                .await?;
+
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            if state.is_first {
+                writer
+                    .write_record(["email", "name", "status", "created_at"])
+                    .map_err(|err| crate::Error::Custom(err.to_string()))?;
+            }
+            for subscriber in &page.subscribers {
+                writer
+                    .write_record([
+                        escape_formula(&subscriber.email).as_ref(),
+                        escape_formula(&subscriber.name).as_ref(),
+                        subscriber.status.as_str(),
+                        subscriber.created_at.as_str(),
+                    ])
+                    .map_err(|err| crate::Error::Custom(err.to_string()))?;
+            }
+            let chunk = writer
+                .into_inner()
+                .map_err(|err| crate::Error::Custom(err.to_string()))?;
+
+            Ok::<_, crate::Error>(Some((
+                chunk,
+                ExportState {
+                    is_done: page.next.is_none(),
+                    after: page.next,
+                    is_first: false,
+                    mm: state.mm,
+                },
+            )))
+        },
+    );
+
+    Ok((
+        [
+            (CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (
+                CONTENT_DISPOSITION,
+                r#"attachment; filename="subscribers.csv""#,
+            ),
+        ],
+        Body::from_stream(batches),
+    ))
+}
+
+/// Keep spreadsheet applications from evaluating a cell as a formula, since
+/// names and emails come from untrusted subscribers.
+fn escape_formula(cell: &str) -> Cow<'_, str> {
+    if cell.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        Cow::Owned(format!("'{cell}"))
+    } else {
+        Cow::Borrowed(cell)
+    }
+}
+
+pub async fn import_subscribers_form(messages: Messages) -> Result<impl IntoResponse> {
+    let flash_messages = messages
+        .into_iter()
+        .map(|message| format!("<p><i>{}</i></p>", message.message))
+        .collect::<Vec<_>>()
+        .join("");
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Import subscribers</title>
+        </head>
+        <body>
+            {flash_messages}
+            <p>Upload a CSV file of up to 5 MiB with <code>email</code> and <code>name</code>
+            columns. Emails that are already known are left untouched.</p>
+            <form action="/admin/subscribers/import" method="post" enctype="multipart/form-data">
+                <label>CSV file
+                    <input type="file" name="file" accept=".csv,text/csv">
+                </label>
+                <br>
+                <label>
+                    <input type="radio" name="mode" value="double_opt_in" checked>
+                    Send a confirmation email to every imported contact
+                </label>
+                <br>
+                <label>
+                    <input type="radio" name="mode" value="confirmed">
+                    Import as confirmed subscribers
+                </label>
+                <br>
+                <label>
+                    <input type="checkbox" name="attestation">
+                    I attest that these contacts already agreed to receive our newsletter
+                </label>
+                <br>
+                <button type="submit">Import</button>
+            </form>
+            <p><a href="/admin/subscribers">&lt;- Back</a></p>
+        </body>
+        </html>
+        "#
+    );
+
+    Ok((StatusCode::OK, Html(body)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImportMode {
+    /// Imported contacts are pending until they follow the emailed link.
+    DoubleOptIn,
+    /// Imported contacts are confirmed right away, which requires an attestation.
+    Confirmed,
+}
+
+/// A row of the uploaded file that was not imported.
+struct SkippedRow {
+    line: u64,
+    email: String,
+    reason: String,
+}
+
+#[tracing::instrument(skip_all, fields(username = %user.username))]
+pub async fn import_subscribers(
+    State(mm): State<Arc<ModelManager>>,
+    messages: Messages,
+    user: AuthenticatedUser,
+    mut multipart: Multipart,
+) -> Result<Response> {
+    let mut file = None;
+    let mut mode = None;
+    let mut attestation = false;
+
+    while let Some(field) = multipart.next_field().await.map_err(invalid_upload)? {
+        match field.name() {
+            Some("file") => file = Some(field.bytes().await.map_err(invalid_upload)?),
+            Some("mode") => {
+                mode = match field.text().await.map_err(invalid_upload)?.as_str() {
+                    "double_opt_in" => Some(ImportMode::DoubleOptIn),
+                    "confirmed" => Some(ImportMode::Confirmed),
+                    _ => None,
+                }
+            }
+            Some("attestation") => attestation = true,
+            _ => {}
+        }
+    }
+
+    let Some(file) = file.filter(|file| !file.is_empty()) else {
+        messages.error("Choose a CSV file to import.");
+        return Ok(Redirect::to("/admin/subscribers/import").into_response());
+    };
+    let Some(mode) = mode else {
+        messages.error("Choose how the contacts should be imported.");
+        return Ok(Redirect::to("/admin/subscribers/import").into_response());
+    };
+    if mode == ImportMode::Confirmed && !attestation {
+        messages.error("Importing confirmed subscribers requires attesting their consent.");
+        return Ok(Redirect::to("/admin/subscribers/import").into_response());
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(file.as_ref());
+    let headers = match reader.headers() {
+        Ok(headers)
+            if headers.iter().any(|h| h == "email") && headers.iter().any(|h| h == "name") =>
+        {
+            headers.clone()
+        }
+        _ => {
+            messages.error("The CSV file must have a header row with `email` and `name` columns.");
+            return Ok(Redirect::to("/admin/subscribers/import").into_response());
+        }
+    };
+
+    let status = match mode {
+        ImportMode::DoubleOptIn => SubscriptionStatus::Pending,
+        ImportMode::Confirmed => SubscriptionStatus::Confirmed,
+    };
+    let mut imported = 0;
+    let mut skipped = Vec::new();
+
+    for record in reader.records() {
+        let (line, form) = match record {
+            Ok(record) => (
+                record.position().map_or(0, |position| position.line()),
+                record.deserialize::<FormData>(Some(&headers)),
+            ),
+            Err(err) => {
+                skipped.push(SkippedRow {
+                    line: err.position().map_or(0, |position| position.line()),
+                    email: String::new(),
+                    reason: err.to_string(),
+                });
+                continue;
+            }
+        };
+
+        let form = match form {
+            Ok(form) => form,
+            Err(err) => {
+                skipped.push(SkippedRow {
+                    line,
+                    email: String::new(),
+                    reason: err.to_string(),
+                });
+                continue;
+            }
+        };
+
+        let email = form.email.clone();
+        let subscriber = match Subscriber::try_from(form) {
+            Ok(subscriber) => subscriber,
+            Err(err) => {
+                skipped.push(SkippedRow {
+                    line,
+                    email,
+                    reason: err.to_string(),
+                });
+                continue;
+            }
+        };
+
+        if !mm
+            .import_subscriber(
+                &subscriber,
+                status,
+                &get_random_token(),
+                &get_random_token(),
+            )
+            .await?
+        {
+            skipped.push(SkippedRow {
+                line,
+                email,
+                reason: "Already known".into(),
+            });
+            continue;
+        }
+        imported += 1;
+    }
+
+    tracing::info!(
+        "Imported {imported} subscribers as {}, skipped {} rows",
+        status.as_str(),
+        skipped.len()
+    );
+
+    Ok((
+        StatusCode::OK,
+        Html(import_report(mode, imported, &skipped)),
+    )
+        .into_response())
+}
+
+fn import_report(mode: ImportMode, imported: usize, skipped: &[SkippedRow]) -> String {
+    let summary = match mode {
+        ImportMode::DoubleOptIn => {
+            format!("{imported} contacts were imported and asked to confirm their subscription.")
+        }
+        ImportMode::Confirmed => {
+            format!("{imported} contacts were imported as confirmed subscribers.")
+        }
+    };
+
+    let rows = skipped
+        .iter()
+        .map(|row| {
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                row.line,
+                encode_minimal(&row.email),
+                encode_minimal(&row.reason),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("");
+
+    format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Import report</title>
+        </head>
+        <body>
+            <p>{summary}</p>
+            <p>{skipped} rows need attention:</p>
+            <table>
+                <thead>
+                    <tr>
+                        <th>Line</th>
+                        <th>Email</th>
+                        <th>Reason</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    {rows}
+                </tbody>
+            </table>
+            <p><a href="/admin/subscribers">&lt;- Back</a></p>
+        </body>
+        </html>
+        "#,
+        skipped = skipped.len(),
+    )
+}
+
+fn invalid_upload(err: axum::extract::multipart::MultipartError) -> Error {
+    if err.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        return Error::Rejected {
+            status: StatusCode::PAYLOAD_TOO_LARGE,
+            code: "UPLOAD_TOO_LARGE",
+            detail: "The CSV file is larger than 5 MiB",
+        };
+    }
+
+    ValidationError::new("INVALID_UPLOAD")
+        .with_message(err.body_text().into())
+        .into()
+}
@@ -0,0 +1,192 @@
+use crate::{
+    Result,
+    authentication::{AuthenticatedUser, verify_second_factor},
+    domain::{SystemClock, TotpSecret, generate_recovery_codes},
+    model::ModelManager,
+    session_state::TypedSession,
+};
+use axum::{
+    Form,
+    extract::State,
+    http::header::CACHE_CONTROL,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use axum_messages::Messages;
+use htmlescape::encode_minimal;
+use reqwest::StatusCode;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use std::sync::Arc;
+
+#[derive(Debug, Deserialize)]
+pub struct CodeForm {
+    code: SecretString,
+}
+
+/// Show the status of two-factor authentication, or let the user enroll their
+/// authenticator app.
+pub async fn admin_two_factor(
+    State(mm): State<Arc<ModelManager>>,
+    messages: Messages,
+    session: TypedSession,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse> {
+    let flash_messages = messages
+        .into_iter()
+        .map(|message| format!("<p><i>{}</i></p>", message.message))
+        .collect::<Vec<_>>()
+        .join("");
+
+    let content = match mm.get_two_factor(&user.id).await? {
+        Some(two_factor) => format!(
+            r#"
+            <p>Two-factor authentication is enabled, {recovery_codes_left} recovery codes left.</p>
+            <form action="/admin/two-factor/disable" method="post">
+                <label>Code
+                    <input
+                        type="text"
+                        autocomplete="one-time-code"
+                        placeholder="Code from your authenticator app, or a recovery code"
+                        name="code"
+                    >
+                </label>
+                <button type="submit">Disable two-factor authentication</button>
+            </form>
+            "#,
+            recovery_codes_left = two_factor.recovery_codes_left,
+        ),
+        None => {
+            // Keep the same secret until it's confirmed, in case the page is reloaded
+            let secret = match session.get_totp_enrollment().await? {
+                Some(secret) => secret,
+                None => {
+                    let secret = TotpSecret::generate();
+                    session.insert_totp_enrollment(&secret).await?;
+                    secret
+                }
+            };
+            format!(
+                r#"
+                <p>Scan this QR code with your authenticator app:</p>
+                {qr_code}
+                <p>Or add this key by hand: <code id="totp-secret">{secret}</code></p>
+                <p><a href="{otpauth_uri}">{otpauth_uri}</a></p>
+                <form action="/admin/two-factor" method="post">
+                    <label>Code
+                        <input
+                            type="text"
+                            inputmode="numeric"
+                            autocomplete="one-time-code"
+                            placeholder="Code shown by the app"
+                            name="code"
+                        >
+                    </label>
+                    <button type="submit">Enable two-factor authentication</button>
+                </form>
+                "#,
+                qr_code = secret.qr_code_svg(&user.username),
+                secret = secret.as_base32(),
+                otpauth_uri = encode_minimal(&secret.otpauth_uri(&user.username)),
+            )
+        }
+    };
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Two-factor authentication</title>
+        </head>
+        <body>
+            {flash_messages}
+            {content}
+            <p><a href="/admin/dashboard">&lt;- Back</a></p>
+        </body>
+        </html>
+        "#
+    );
+
+    Ok((StatusCode::OK, [(CACHE_CONTROL, "no-store")], Html(body)).into_response())
+}
+
+/// Enable two-factor authentication once the enrolled app gave a valid code,
+/// showing the recovery codes, the only time they can be read.
+pub async fn admin_enable_two_factor(
+    State(mm): State<Arc<ModelManager>>,
+    messages: Messages,
+    session: TypedSession,
+    user: AuthenticatedUser,
+    Form(form): Form<CodeForm>,
+) -> Result<Response> {
+    if mm.get_two_factor(&user.id).await?.is_some() {
+        messages.warning("Two-factor authentication is already enabled.");
+        return Ok(Redirect::to("/admin/two-factor").into_response());
+    }
+    let Some(secret) = session.get_totp_enrollment().await? else {
+        return Ok(Redirect::to("/admin/two-factor").into_response());
+    };
+    let Some(step) = secret.verify(form.code.expose_secret(), &SystemClock, None) else {
+        messages.error("The code is invalid, check the clock of your device and try again.");
+        return Ok(Redirect::to("/admin/two-factor").into_response());
+    };
+
+    let recovery_codes = generate_recovery_codes();
+    mm.enable_two_factor(&user.id, &secret, step, &recovery_codes)
+        .await?;
+    session.remove_totp_enrollment().await?;
+    tracing::info!("{} enabled two-factor authentication", user.username);
+
+    let recovery_codes = recovery_codes
+        .iter()
+        .map(|code| format!("<li><code>{code}</code></li>"))
+        .collect::<Vec<_>>()
+        .join("");
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Two-factor authentication enabled</title>
+        </head>
+        <body>
+            <p>Two-factor authentication is enabled.</p>
+            <p>Each of these recovery codes lets you log in once without your authenticator app:</p>
+            <ul id="recovery-codes">{recovery_codes}</ul>
+            <p>Store them somewhere safe, they won't be shown again.</p>
+            <p><a href="/admin/dashboard">&lt;- Back</a></p>
+        </body>
+        </html>
+        "#
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        [(CACHE_CONTROL, "no-store")],
+        Html(body),
+    )
+        .into_response())
+}
+
+pub async fn admin_disable_two_factor(
+    State(mm): State<Arc<ModelManager>>,
+    messages: Messages,
+    user: AuthenticatedUser,
+    Form(form): Form<CodeForm>,
+) -> Result<impl IntoResponse> {
+    let Some(two_factor) = mm.get_two_factor(&user.id).await? else {
+        return Ok(Redirect::to("/admin/two-factor"));
+    };
+    let code = form.code.expose_secret();
+    if !verify_second_factor(&mm, &user.id, &two_factor, code, &SystemClock).await? {
+        messages.error("The code is invalid.");
+        return Ok(Redirect::to("/admin/two-factor"));
+    }
+
+    mm.reset_two_factor(&user.username).await?;
+    tracing::info!("{} disabled two-factor authentication", user.username);
+    messages.info("Two-factor authentication is disabled.");
+    Ok(Redirect::to("/admin/two-factor"))
+}
@@ -0,0 +1,26 @@
+mod newsletters;
+mod subscribers;
+mod subscriptions;
+
+pub use newsletters::*;
+pub use subscribers::*;
+pub use subscriptions::*;
+
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Body of every successful `/api/v1` response, errors being answered with
+/// `application/problem+json`.
+#[derive(Debug, Serialize, ToSchema)]
+struct Envelope<T> {
+    data: T,
+}
+
+fn respond<T: Serialize>(status: StatusCode, data: T) -> Response {
+    (status, Json(Envelope { data })).into_response()
+}
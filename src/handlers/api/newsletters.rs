@@ -0,0 +1,59 @@
+use super::{Envelope, respond};
+use crate::{
+    Config, Result,
+    authentication::AuthenticatedUser,
+    errors::Problem,
+    handlers::{BodyData, JsonBody, newsletter::publish_idempotently},
+    idempotency,
+    model::{IdempotencyEndpoint, ModelManager},
+};
+use axum::{extract::State, http::HeaderMap, response::Response};
+use reqwest::StatusCode;
+use serde::Serialize;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+struct NewsletterData {
+    recipients: usize,
+}
+
+/// Send a newsletter issue to every confirmed subscriber.
+#[utoipa::path(
+    post,
+    path = "/api/v1/newsletters",
+    tag = "api",
+    request_body = BodyData,
+    params(
+        ("Idempotency-Key" = Option<String>, Header, description = "Retries with the same key publish the issue only once"),
+    ),
+    security(("bearer_auth" = ["newsletter:publish"]), ("basic_auth" = [])),
+    responses(
+        (status = ACCEPTED, description = "The issue is queued for delivery", body = Envelope<NewsletterData>),
+        (status = BAD_REQUEST, description = "The body or idempotency key is invalid", body = Problem, content_type = "application/problem+json"),
+        (status = UNAUTHORIZED, description = "The credentials are missing or invalid", body = Problem, content_type = "application/problem+json"),
+        (status = FORBIDDEN, description = "The API token lacks the `newsletter:publish` scope", body = Problem, content_type = "application/problem+json"),
+        (status = CONFLICT, description = "A request with the same idempotency key is in progress"),
+    )
+)]
+#[tracing::instrument(skip(mm, config, user, body))]
+pub async fn api_publish_newsletter(
+    State(mm): State<Arc<ModelManager>>,
+    State(config): State<Arc<Config>>,
+    user: AuthenticatedUser,
+    headers: HeaderMap,
+    JsonBody(body): JsonBody<BodyData>,
+) -> Result<Response> {
+    let idempotency_key = idempotency::get_idempotency_key(&headers)?;
+
+    publish_idempotently(
+        &mm,
+        &config.idempotency,
+        &user.id,
+        IdempotencyEndpoint::ApiNewsletters,
+        idempotency_key,
+        &body,
+        |recipients| respond(StatusCode::ACCEPTED, NewsletterData { recipients }),
+    )
+    .await
+}
@@ -0,0 +1,66 @@
+use super::{Envelope, respond};
+use crate::{
+    Error, Result,
+    errors::Problem,
+    model::{ModelManager, SubscriptionStatus},
+};
+use axum::{
+    extract::{Path, State},
+    response::Response,
+};
+use reqwest::StatusCode;
+use serde::Serialize;
+use std::sync::Arc;
+use surrealdb::RecordId;
+use utoipa::ToSchema;
+
+const SUBSCRIPTIONS_TABLE: &str = "subscriptions";
+
+#[derive(Debug, Serialize, ToSchema)]
+struct SubscriberData {
+    id: String,
+    email: String,
+    name: String,
+    status: SubscriptionStatus,
+    created_at: String,
+}
+
+/// Look a subscriber up by id.
+#[utoipa::path(
+    get,
+    path = "/api/v1/subscribers/{id}",
+    tag = "api",
+    params(("id" = String, Path, description = "The id of the subscriber", example = "0mtqhnkvh3sqb5dsrlzs")),
+    security(("bearer_auth" = ["subscribers:read"]), ("basic_auth" = [])),
+    responses(
+        (status = OK, description = "The subscriber", body = Envelope<SubscriberData>),
+        (status = UNAUTHORIZED, description = "The credentials are missing or invalid", body = Problem, content_type = "application/problem+json"),
+        (status = FORBIDDEN, description = "The API token lacks the `subscribers:read` scope", body = Problem, content_type = "application/problem+json"),
+        (status = NOT_FOUND, description = "There is no subscriber with this id", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[tracing::instrument(skip(mm))]
+pub async fn api_subscriber(
+    State(mm): State<Arc<ModelManager>>,
+    Path(key): Path<String>,
+) -> Result<Response> {
+    let subscriber = mm
+        .get_subscriber(RecordId::from_table_key(SUBSCRIPTIONS_TABLE, key))
+        .await?
+        .ok_or(Error::Rejected {
+            status: StatusCode::NOT_FOUND,
+            code: "SUBSCRIBER_NOT_FOUND",
+            detail: "There is no subscriber with this id",
+        })?;
+
+    Ok(respond(
+        StatusCode::OK,
+        SubscriberData {
+            id: subscriber.id.key().to_string(),
+            email: subscriber.email,
+            name: subscriber.name,
+            status: subscriber.status,
+            created_at: subscriber.created_at,
+        },
+    ))
+}
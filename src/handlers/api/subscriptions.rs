@@ -0,0 +1,131 @@
+use super::{Envelope, respond};
+use crate::{
+    Config, Error, Result,
+    domain::Subscriber,
+    email_client::EmailSender,
+    errors::Problem,
+    handlers::{FormData, JsonBody, subscription::register_subscriber},
+    model::{ConfirmationOutcome, ModelManager, SubscriptionStatus},
+};
+use axum::{extract::State, response::Response};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TokenData {
+    #[schema(example = "Jq2xV8rN0pLs4TbW7cYk1mHfA")]
+    token: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct SubscriptionData<T> {
+    status: T,
+}
+
+/// Subscribe to the newsletter, a confirmation email is sent to `email`.
+#[utoipa::path(
+    post,
+    path = "/api/v1/subscriptions",
+    tag = "api",
+    request_body = FormData,
+    responses(
+        (status = CREATED, description = "A confirmation email is on its way", body = Envelope<SubscriptionData<SubscriptionStatus>>),
+        (status = BAD_REQUEST, description = "The body is malformed or invalid", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[tracing::instrument(skip(mm, config, email_client))]
+pub async fn api_subscribe(
+    State(mm): State<Arc<ModelManager>>,
+    State(config): State<Arc<Config>>,
+    State(email_client): State<Arc<dyn EmailSender>>,
+    JsonBody(body): JsonBody<FormData>,
+) -> Result<Response> {
+    let subscriber: Subscriber = body.try_into()?;
+    register_subscriber(&mm, &config, email_client.as_ref(), &subscriber).await?;
+
+    // Known emails get the same answer, see `register_subscriber`
+    Ok(respond(
+        StatusCode::CREATED,
+        SubscriptionData {
+            status: SubscriptionStatus::Pending,
+        },
+    ))
+}
+
+/// Confirm a subscription with the token of the confirmation email.
+#[utoipa::path(
+    post,
+    path = "/api/v1/subscriptions/confirm",
+    tag = "api",
+    request_body = TokenData,
+    responses(
+        (status = OK, description = "The subscription is confirmed", body = Envelope<SubscriptionData<ConfirmationOutcome>>),
+        (status = BAD_REQUEST, description = "The body is malformed", body = Problem, content_type = "application/problem+json"),
+        (status = NOT_FOUND, description = "The token is unknown or was replaced", body = Problem, content_type = "application/problem+json"),
+        (status = GONE, description = "The token has expired", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[tracing::instrument(skip(mm, config))]
+pub async fn api_confirm(
+    State(mm): State<Arc<ModelManager>>,
+    State(config): State<Arc<Config>>,
+    JsonBody(body): JsonBody<TokenData>,
+) -> Result<Response> {
+    let TokenData { token } = body;
+
+    match mm
+        .confirm_subscriber(token, config.confirmation.token_ttl)
+        .await?
+    {
+        outcome @ (ConfirmationOutcome::Confirmed | ConfirmationOutcome::AlreadyConfirmed) => Ok(
+            respond(StatusCode::OK, SubscriptionData { status: outcome }),
+        ),
+        ConfirmationOutcome::Expired => Err(Error::Rejected {
+            status: StatusCode::GONE,
+            code: "CONFIRMATION_TOKEN_EXPIRED",
+            detail: "The confirmation token has expired, subscribe again to receive a new one",
+        }),
+        ConfirmationOutcome::InvalidToken => Err(Error::Rejected {
+            status: StatusCode::NOT_FOUND,
+            code: "INVALID_CONFIRMATION_TOKEN",
+            detail: "The confirmation token is unknown or was replaced by a newer one",
+        }),
+    }
+}
+
+/// Unsubscribe with the token of any newsletter email.
+#[utoipa::path(
+    post,
+    path = "/api/v1/subscriptions/unsubscribe",
+    tag = "api",
+    request_body = TokenData,
+    responses(
+        (status = OK, description = "The subscriber won't receive any other issue", body = Envelope<SubscriptionData<SubscriptionStatus>>),
+        (status = BAD_REQUEST, description = "The body is malformed", body = Problem, content_type = "application/problem+json"),
+        (status = NOT_FOUND, description = "The token is unknown", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[tracing::instrument(skip(mm))]
+pub async fn api_unsubscribe(
+    State(mm): State<Arc<ModelManager>>,
+    JsonBody(body): JsonBody<TokenData>,
+) -> Result<Response> {
+    let TokenData { token } = body;
+
+    if !mm.unsubscribe(token).await? {
+        return Err(Error::Rejected {
+            status: StatusCode::NOT_FOUND,
+            code: "INVALID_UNSUBSCRIBE_TOKEN",
+            detail: "The unsubscribe token is unknown",
+        });
+    }
+
+    Ok(respond(
+        StatusCode::OK,
+        SubscriptionData {
+            status: SubscriptionStatus::Unsubscribed,
+        },
+    ))
+}
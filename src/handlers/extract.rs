@@ -0,0 +1,42 @@
+use crate::Error;
+use axum::{
+    Form, Json,
+    extract::{FromRequest, Request},
+};
+use serde::de::DeserializeOwned;
+
+/// [`Json`] whose malformed bodies are answered with a problem, like any
+/// other invalid request.
+#[derive(Debug)]
+pub struct JsonBody<T>(pub T);
+
+impl<T, S> FromRequest<S> for JsonBody<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(body) = Json::from_request(request, state).await?;
+        Ok(Self(body))
+    }
+}
+
+/// [`Form`] whose malformed bodies are answered with a problem, like any
+/// other invalid request.
+#[derive(Debug)]
+pub struct FormBody<T>(pub T);
+
+impl<T, S> FromRequest<S> for FormBody<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Form(body) = Form::from_request(request, state).await?;
+        Ok(Self(body))
+    }
+}
@@ -1,8 +1,64 @@
-use crate::Result;
 use crate::model::ModelManager;
-use axum::extract::State;
+use crate::{Error, Result, state::AppState};
+use axum::{Json, extract::State, response::IntoResponse};
 use reqwest::StatusCode;
-use std::sync::Arc;
+use serde::Serialize;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+
+/// How long the outcome of the email provider check is reused, sparing the
+/// provider an API call per probe.
+const EMAIL_CHECK_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    status: CheckStatus,
+    checks: Vec<Check>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    name: &'static str,
+    status: CheckStatus,
+    latency_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Up,
+    Down,
+}
+
+/// The last outcome of a check too costly to run on every probe.
+#[derive(Debug, Default)]
+pub struct CachedCheck(Mutex<Option<(Instant, Check)>>);
+
+impl CachedCheck {
+    /// The outcome of `run` from less than `ttl` ago, running it again
+    /// otherwise.
+    async fn get_or_run<F>(&self, ttl: Duration, run: impl FnOnce() -> F) -> Check
+    where
+        F: Future<Output = Check>,
+    {
+        // Held while checking, so concurrent probes wait for one check
+        let mut cached = self.0.lock().await;
+        if let Some((checked_at, check)) = cached.as_ref()
+            && checked_at.elapsed() < ttl
+        {
+            return check.clone();
+        }
+
+        let check = run().await;
+        *cached = Some((Instant::now(), check.clone()));
+        check
+    }
+}
 
 #[tracing::instrument(skip(mm))]
 pub async fn health(State(mm): State<Arc<ModelManager>>) -> Result<StatusCode> {
@@ -10,3 +66,74 @@ pub async fn health(State(mm): State<Arc<ModelManager>>) -> Result<StatusCode> {
 
     Ok(StatusCode::OK)
 }
+
+/// Answers as long as the process is able to serve requests.
+pub async fn health_live() -> StatusCode {
+    StatusCode::OK
+}
+
+/// Checks every dependency needed to serve traffic, answering 503 with the
+/// failing checks when the instance should be taken out of rotation. The
+/// email provider is checked at most once per [`EMAIL_CHECK_TTL`].
+#[tracing::instrument(skip(state))]
+pub async fn health_ready(State(state): State<AppState>) -> impl IntoResponse {
+    let (database, migrations, email) = tokio::join!(
+        check("database", async {
+            state.mm.db().await?.health().await?;
+            Ok(())
+        }),
+        check("migrations", async {
+            let status = state.mm.migration_status().await?;
+            if status.pending.is_empty() {
+                Ok(())
+            } else {
+                Err(Error::Migrations(format!(
+                    "Pending migrations: {}",
+                    status.pending.join(", ")
+                )))
+            }
+        }),
+        state.email_check.get_or_run(EMAIL_CHECK_TTL, || {
+            check("email", async {
+                state.email_client.check_health().await?;
+                Ok(())
+            })
+        }),
+    );
+
+    let checks = vec![database, migrations, email];
+    let status = if checks.iter().all(|check| check.status == CheckStatus::Up) {
+        CheckStatus::Up
+    } else {
+        tracing::warn!("Instance is not ready: {checks:?}");
+        CheckStatus::Down
+    };
+    let status_code = match status {
+        CheckStatus::Up => StatusCode::OK,
+        CheckStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+    };
+
+    (status_code, Json(Readiness { status, checks }))
+}
+
+/// Run `probe`, timing it.
+async fn check(name: &'static str, probe: impl Future<Output = Result<()>>) -> Check {
+    let started_at = Instant::now();
+    let outcome = probe.await;
+    let latency_ms = started_at.elapsed().as_millis();
+
+    match outcome {
+        Ok(()) => Check {
+            name,
+            status: CheckStatus::Up,
+            latency_ms,
+            error: None,
+        },
+        Err(err) => Check {
+            name,
+            status: CheckStatus::Down,
+            latency_ms,
+            error: Some(err.to_string()),
+        },
+    }
+}
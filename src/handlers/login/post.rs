@@ -23,6 +23,7 @@ pub async fn login(
     session: TypedSession,
     Form(form): Form<FormData>,
 ) -> Result<impl IntoResponse> {
+    let username = form.username.clone();
     let credentials = Credentials {
         username: form.username,
         password: form.password,
@@ -36,7 +37,12 @@ pub async fn login(
     match result {
         Ok(record_id) => {
             session.renew().await?;
+            if mm.get_two_factor(&record_id).await?.is_some() {
+                session.insert_pending_user_id(record_id).await?;
+                return Ok(Redirect::to("/login/two-factor"));
+            }
             session.insert_user_id(record_id).await?;
+            session.insert_username(username).await?;
             Ok(Redirect::to("/admin/dashboard"))
         }
         Err(err) => {
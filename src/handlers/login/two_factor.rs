@@ -0,0 +1,112 @@
+use crate::{
+    Result, authentication::verify_second_factor, domain::SystemClock, model::ModelManager,
+    session_state::TypedSession,
+};
+use axum::{
+    Form,
+    extract::State,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use axum_messages::Messages;
+use reqwest::StatusCode;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use std::{sync::Arc, time::Duration};
+
+/// Wrong codes allowed before the second factor is locked. They are counted
+/// on the user, so that giving the password again doesn't start over.
+const MAX_FAILED_CODES: u32 = 5;
+/// How long no code is accepted once too many wrong ones were given.
+const LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Deserialize)]
+pub struct FormData {
+    code: SecretString,
+}
+
+pub async fn form(messages: Messages, session: TypedSession) -> Result<Response> {
+    if session.get_pending_user_id().await?.is_none() {
+        return Ok(Redirect::to("/login").into_response());
+    }
+
+    let error_message = messages
+        .into_iter()
+        .map(|message| format!("<p><i>{}</i></p>", message.message))
+        .collect::<Vec<_>>()
+        .join("");
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Two-factor authentication</title>
+        </head>
+        <body>
+            {error_message}
+            <form action="/login/two-factor" method="post">
+                <label>Code
+                    <input
+                        type="text"
+                        autocomplete="one-time-code"
+                        placeholder="Code from your authenticator app, or a recovery code"
+                        name="code"
+                    >
+                </label>
+                <button type="submit">Verify</button>
+            </form>
+        </body>
+        </html>
+        "#
+    );
+
+    Ok((StatusCode::OK, Html(body)).into_response())
+}
+
+/// Complete the login of a user who gave the right password.
+pub async fn verify(
+    State(mm): State<Arc<ModelManager>>,
+    messages: Messages,
+    session: TypedSession,
+    Form(form): Form<FormData>,
+) -> Result<impl IntoResponse> {
+    let Some(user_id) = session.get_pending_user_id().await? else {
+        return Ok(Redirect::to("/login"));
+    };
+    let Some(two_factor) = mm.get_two_factor(&user_id).await? else {
+        // Reset since the password was checked, start over
+        session.remove_pending_user_id().await?;
+        return Ok(Redirect::to("/login"));
+    };
+
+    if two_factor.locked {
+        tracing::warn!("Second factor of {user_id} is locked");
+        session.remove_pending_user_id().await?;
+        messages.warning("Too many invalid codes, try again later");
+        return Ok(Redirect::to("/login"));
+    }
+
+    let code = form.code.expose_secret();
+    if verify_second_factor(&mm, &user_id, &two_factor, code, &SystemClock).await? {
+        mm.clear_failed_codes(&user_id).await?;
+        session.remove_pending_user_id().await?;
+        session.renew().await?;
+        let username = mm.get_username(user_id.clone()).await?;
+        session.insert_user_id(user_id).await?;
+        session.insert_username(username).await?;
+        return Ok(Redirect::to("/admin/dashboard"));
+    }
+
+    tracing::warn!("Invalid second factor given for {user_id}");
+    if mm
+        .record_failed_code(&user_id, MAX_FAILED_CODES, LOCKOUT)
+        .await?
+    {
+        session.remove_pending_user_id().await?;
+        messages.warning("Too many invalid codes, try again later");
+        return Ok(Redirect::to("/login"));
+    }
+    messages.warning("Invalid code");
+    Ok(Redirect::to("/login/two-factor"))
+}
@@ -1,12 +1,22 @@
 mod admin;
+mod api;
+mod extract;
 mod health_check;
 mod home;
 pub mod login;
 mod newsletter;
+mod openapi;
+mod prometheus;
 mod subscription;
+mod unsubscribe;
 
 pub use admin::*;
+pub use api::*;
+pub use extract::*;
 pub use health_check::*;
 pub use home::*;
 pub use newsletter::*;
+pub use openapi::*;
+pub use prometheus::*;
 pub use subscription::*;
+pub use unsubscribe::*;
@@ -1,49 +1,171 @@
-use crate::{Error, Result, email_client::EmailClient, model::ModelManager};
-use axum::{Json, extract::State, http::HeaderMap, response::IntoResponse};
+use crate::{
+    Config, Error, Result,
+    authentication::AuthenticatedUser,
+    config::IdempotencyConfig,
+    domain::IdempotencyKey,
+    errors::Problem,
+    handlers::JsonBody,
+    idempotency::{self, NextAction},
+    model::{IdempotencyEndpoint, ModelManager},
+};
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+};
 use base64::{Engine, prelude::BASE64_STANDARD};
 use reqwest::{StatusCode, header::AUTHORIZATION};
 use secrecy::SecretString;
 use serde::Deserialize;
 use std::sync::Arc;
+use surrealdb::RecordId;
+use utoipa::ToSchema;
+use validator::{ValidationError, ValidationErrors};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct BodyData {
+    #[schema(example = "Issue #42")]
     title: String,
     content: Content,
 }
 
-#[derive(Debug, Deserialize)]
+impl BodyData {
+    pub(crate) fn new(title: String, html: String, text: String) -> Self {
+        Self {
+            title,
+            content: Content { html, text },
+        }
+    }
+
+    /// Reject issues missing their title or either rendering of the content.
+    fn validate(&self) -> std::result::Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        let fields = [
+            ("title", &self.title, "INVALID_NEWSLETTER_TITLE"),
+            (
+                "content.html",
+                &self.content.html,
+                "INVALID_NEWSLETTER_CONTENT",
+            ),
+            (
+                "content.text",
+                &self.content.text,
+                "INVALID_NEWSLETTER_CONTENT",
+            ),
+        ];
+        for (field, value, code) in fields {
+            if value.trim().is_empty() {
+                errors.add(
+                    field,
+                    ValidationError::new(code).with_message(format!("{field} is empty").into()),
+                );
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// The same issue rendered for HTML and plain text email clients.
+#[derive(Debug, Deserialize, ToSchema)]
 struct Content {
+    #[schema(example = "<p>Hello, reader!</p>")]
     html: String,
+    #[schema(example = "Hello, reader!")]
     text: String,
 }
 
-#[tracing::instrument(skip(mm, email_client))]
+/// Send a newsletter issue to every confirmed subscriber.
+#[utoipa::path(
+    post,
+    path = "/newsletter",
+    tag = "newsletters",
+    request_body = BodyData,
+    params(
+        ("Idempotency-Key" = Option<String>, Header, description = "Retries with the same key publish the issue only once"),
+    ),
+    security(("bearer_auth" = ["newsletter:publish"]), ("basic_auth" = [])),
+    responses(
+        (status = ACCEPTED, description = "The issue is queued for delivery"),
+        (status = BAD_REQUEST, description = "The body or the idempotency key is invalid", body = Problem, content_type = "application/problem+json"),
+        (status = UNAUTHORIZED, description = "The credentials are missing or invalid", body = Problem, content_type = "application/problem+json"),
+        (status = FORBIDDEN, description = "The API token lacks the `newsletter:publish` scope", body = Problem, content_type = "application/problem+json"),
+        (status = CONFLICT, description = "A request with the same idempotency key is in progress"),
+    )
+)]
+#[tracing::instrument(skip(mm, config, user))]
 pub async fn publish_newsletter(
     State(mm): State<Arc<ModelManager>>,
-    State(email_client): State<Arc<EmailClient>>,
+    State(config): State<Arc<Config>>,
+    user: AuthenticatedUser,
     headers: HeaderMap,
-    Json(body): Json<BodyData>,
-) -> Result<impl IntoResponse> {
-    let credentials = basic_authentication(headers).await?;
-    _ = mm
-        .validate_credientials(credentials)
-        .await
-        .map_err(|err| Error::Auth(err.to_string()))?;
-    let subscribers = mm.get_confirmed_subscribers().await?;
-
-    for subscriber in subscribers {
-        email_client
-            .send_email(
-                &subscriber.email.try_into()?,
-                &body.title,
-                &body.content.html,
-                &body.content.text,
-            )
-            .await?;
+    JsonBody(body): JsonBody<BodyData>,
+) -> Result<Response> {
+    let idempotency_key = idempotency::get_idempotency_key(&headers)?;
+
+    publish_idempotently(
+        &mm,
+        &config.idempotency,
+        &user.id,
+        IdempotencyEndpoint::Newsletter,
+        idempotency_key,
+        &body,
+        |_| StatusCode::ACCEPTED.into_response(),
+    )
+    .await
+}
+
+/// Queue `body` for delivery, at most once per idempotency key of `user_id`
+/// on `endpoint`, answering with `respond` given the number of recipients.
+///
+/// A key left in progress by a request that never saved its response is taken
+/// over once its claim expires.
+pub(crate) async fn publish_idempotently(
+    mm: &ModelManager,
+    config: &IdempotencyConfig,
+    user_id: &RecordId,
+    endpoint: IdempotencyEndpoint,
+    idempotency_key: Option<IdempotencyKey>,
+    body: &BodyData,
+    respond: impl FnOnce(usize) -> Response,
+) -> Result<Response> {
+    body.validate()?;
+
+    let Some(idempotency_key) = idempotency_key else {
+        return Ok(respond(enqueue_newsletter(mm, body).await?));
+    };
+
+    match idempotency::try_processing(mm, user_id, endpoint, &idempotency_key, config).await? {
+        NextAction::StartProcessing => {}
+        NextAction::InProgress => return Ok(StatusCode::CONFLICT.into_response()),
+        NextAction::ReturnSavedResponse(response) => return Ok(response),
     }
 
-    Ok(StatusCode::OK)
+    match enqueue_newsletter(mm, body).await {
+        Ok(queued) => {
+            idempotency::save_response(mm, user_id, endpoint, &idempotency_key, respond(queued))
+                .await
+        }
+        Err(err) => {
+            // Release the key so the client is able to retry
+            mm.delete_idempotency_key(user_id, endpoint, &idempotency_key)
+                .await?;
+            Err(err)
+        }
+    }
+}
+
+async fn enqueue_newsletter(mm: &ModelManager, body: &BodyData) -> Result<usize> {
+    let queued = mm
+        .enqueue_newsletter_issue(&body.title, &body.content.text, &body.content.html)
+        .await?;
+    tracing::info!("Newsletter issue queued for {queued} subscribers");
+
+    Ok(queued)
 }
 
 pub struct Credentials {
@@ -51,7 +173,7 @@ pub struct Credentials {
     pub password: SecretString,
 }
 
-pub async fn basic_authentication(headers: HeaderMap) -> Result<Credentials> {
+pub async fn basic_authentication(headers: &HeaderMap) -> Result<Credentials> {
     let authorization_header = headers
         .get(AUTHORIZATION)
         .ok_or(Error::Auth("The `Authorization` header is messing!".into()))?
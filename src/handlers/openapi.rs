@@ -0,0 +1,63 @@
+use axum::Json;
+use utoipa::{
+    Modify, OpenApi,
+    openapi::{
+        self,
+        security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    },
+};
+
+/// The contract of the endpoints partners integrate with.
+#[derive(OpenApi)]
+#[openapi(
+    info(description = "Subscribe to the newsletter and publish its issues."),
+    paths(
+        super::subscribe,
+        super::confirm,
+        super::publish_newsletter,
+        super::api_subscribe,
+        super::api_confirm,
+        super::api_unsubscribe,
+        super::api_subscriber,
+        super::api_publish_newsletter,
+    ),
+    modifiers(&SecuritySchemes),
+    tags(
+        (name = "subscriptions", description = "Form based subscription flow"),
+        (name = "newsletters", description = "Publishing newsletter issues"),
+        (name = "api", description = "JSON API for the mobile app and partner integrations"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Declare how the endpoints needing an [`ApiScope`](crate::domain::ApiScope)
+/// authenticate their clients.
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_default();
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("An API token issued from the admin area"))
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "basic_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Basic)
+                    .description(Some("Deprecated, admin credentials"))
+                    .build(),
+            ),
+        );
+    }
+}
+
+pub async fn openapi_json() -> Json<openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
@@ -0,0 +1,29 @@
+use crate::{Result, model::SubscriptionStatus, state::AppState};
+use axum::{extract::State, http::header::CONTENT_TYPE, response::IntoResponse};
+
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Every recorded metric in the Prometheus text format.
+#[tracing::instrument(skip(state))]
+pub async fn metrics(State(state): State<AppState>) -> Result<impl IntoResponse> {
+    // Subscriber counts live in the database, refresh them on every scrape
+    let counts = state.mm.count_subscribers_by_status().await?;
+    for status in [
+        SubscriptionStatus::Pending,
+        SubscriptionStatus::Confirmed,
+        SubscriptionStatus::Unsubscribed,
+    ] {
+        let count = counts
+            .iter()
+            .find(|count| count.status == status)
+            .map_or(0, |count| count.count);
+        metrics::gauge!("subscriptions", "status" => status.as_str()).set(count as f64);
+    }
+
+    state.metrics.run_upkeep();
+
+    Ok((
+        [(CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)],
+        state.metrics.render(),
+    ))
+}
@@ -1,53 +1,155 @@
-use crate::{Config, Result, domain::Subscriber, email_client::EmailClient, model::ModelManager};
+use crate::{
+    Config, Result,
+    domain::Subscriber,
+    email_client::EmailSender,
+    errors::Problem,
+    handlers::FormBody,
+    model::{ConfirmationOutcome, ModelManager, SubscribeOutcome},
+};
 use axum::extract::Query;
-use axum::{Form, extract::State};
+use axum::extract::State;
+use axum::response::{Html, IntoResponse, Response};
 use rand::Rng;
 use rand::distr::Alphanumeric;
 use reqwest::StatusCode;
 use serde::Deserialize;
 use std::sync::Arc;
 use url::Url;
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct FormData {
+    #[schema(example = "ursula_le_guin@gmail.com")]
     pub email: String,
+    #[schema(example = "le guin")]
     pub name: String,
 }
 
+/// Subscribe to the newsletter, a confirmation email is sent to `email`.
+#[utoipa::path(
+    post,
+    path = "/subscriptions",
+    tag = "subscriptions",
+    request_body(content = FormData, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = CREATED, description = "A confirmation email is on its way"),
+        (status = BAD_REQUEST, description = "The body is malformed, or the email or name is invalid", body = Problem, content_type = "application/problem+json"),
+    )
+)]
 #[tracing::instrument(skip(mm, config, email_client))]
 pub async fn subscribe(
     State(mm): State<Arc<ModelManager>>,
     State(config): State<Arc<Config>>,
-    State(email_client): State<Arc<EmailClient>>,
-    Form(form): Form<FormData>,
+    State(email_client): State<Arc<dyn EmailSender>>,
+    FormBody(form): FormBody<FormData>,
 ) -> Result<StatusCode> {
     let subscriber: Subscriber = form.try_into()?;
+    register_subscriber(&mm, &config, email_client.as_ref(), &subscriber).await?;
 
-    let token = get_confirmation_token();
-
-    mm.create_subscriber(&subscriber, &token).await?;
+    Ok(StatusCode::CREATED)
+}
 
-    send_confirmation_email(&email_client, &config, &subscriber, &token).await?;
+/// Start the double opt-in of `subscriber`, or let an already confirmed
+/// subscriber know there is nothing else to do.
+pub(crate) async fn register_subscriber(
+    mm: &ModelManager,
+    config: &Config,
+    email_client: &dyn EmailSender,
+    subscriber: &Subscriber,
+) -> Result<()> {
+    let token = get_random_token();
+    let unsubscribe_token = get_random_token();
 
-    Ok(StatusCode::CREATED)
+    // The response is the same whether the email was known or not, so the
+    // endpoint can't be used to find out who is on the list.
+    match mm
+        .create_subscriber(subscriber, &token, &unsubscribe_token)
+        .await?
+    {
+        SubscribeOutcome::Pending => {
+            send_confirmation_email(email_client, config, subscriber, &token).await
+        }
+        SubscribeOutcome::AlreadyConfirmed => {
+            send_already_subscribed_email(email_client, subscriber).await
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct Params {
+    /// The token sent in the confirmation email.
+    #[param(example = "Jq2xV8rN0pLs4TbW7cYk1mHfA")]
     token: String,
 }
 
-#[tracing::instrument(skip(mm))]
+/// Confirm a subscription from the link of the confirmation email.
+#[utoipa::path(
+    get,
+    path = "/subscriptions/confirm",
+    tag = "subscriptions",
+    params(Params),
+    responses(
+        (status = OK, description = "The subscription is confirmed", body = String, content_type = "text/html"),
+        (status = NOT_FOUND, description = "The token is unknown or was replaced", body = String, content_type = "text/html"),
+        (status = GONE, description = "The token has expired", body = String, content_type = "text/html"),
+    )
+)]
+#[tracing::instrument(skip(mm, config))]
 pub async fn confirm(
     State(mm): State<Arc<ModelManager>>,
+    State(config): State<Arc<Config>>,
     Query(params): Query<Params>,
-) -> Result<StatusCode> {
-    mm.confirm_subscriber(params.token.clone()).await?;
+) -> Result<Response> {
+    let outcome = mm
+        .confirm_subscriber(params.token, config.confirmation.token_ttl)
+        .await?;
 
-    Ok(StatusCode::OK)
+    let (status, title, message) = match outcome {
+        ConfirmationOutcome::Confirmed => (
+            StatusCode::OK,
+            "Subscription confirmed",
+            "Thanks for confirming your subscription, you will receive our next issue.",
+        ),
+        ConfirmationOutcome::AlreadyConfirmed => (
+            StatusCode::OK,
+            "Subscription confirmed",
+            "Your subscription was already confirmed, there is nothing else to do.",
+        ),
+        ConfirmationOutcome::Expired => (
+            StatusCode::GONE,
+            "Link expired",
+            "This confirmation link has expired. Subscribe again with the same email to receive a new one.",
+        ),
+        ConfirmationOutcome::InvalidToken => (
+            StatusCode::NOT_FOUND,
+            "Invalid link",
+            "This confirmation link is invalid. It may have been replaced by a newer one.",
+        ),
+    };
+
+    Ok((status, Html(confirmation_page(title, message))).into_response())
 }
 
-fn get_confirmation_token() -> String {
+fn confirmation_page(title: &str, message: &str) -> String {
+    format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>{title}</title>
+        </head>
+        <body>
+            <p>{message}</p>
+            <p><a href="/">Go back to the home page</a></p>
+        </body>
+        </html>
+        "#
+    )
+}
+
+pub fn get_random_token() -> String {
     rand::rng()
         .sample_iter(&Alphanumeric)
         .map(char::from)
@@ -55,26 +157,54 @@ fn get_confirmation_token() -> String {
         .collect()
 }
 
-async fn send_confirmation_email(
-    email_client: &EmailClient,
+pub const CONFIRMATION_EMAIL_SUBJECT: &str = "Welcome!";
+
+/// The HTML and plain text bodies of the email asking to confirm a
+/// subscription with `token`.
+pub fn confirmation_email_content(config: &Config, token: &str) -> Result<(String, String)> {
+    let confirmation_link = get_confirmation_link(config, token)?;
+
+    Ok((
+        format!(
+            "Welcome to our newsletter!<br />Click <a href=\"{}\">here</a> to confirm your subscription.",
+            confirmation_link
+        ),
+        format!(
+            "Welcome to our newsletter!\nVisit {} to confirm your subscription.",
+            confirmation_link
+        ),
+    ))
+}
+
+pub async fn send_confirmation_email(
+    email_client: &dyn EmailSender,
     config: &Config,
     subscriber: &Subscriber,
     token: &str,
 ) -> Result<()> {
-    let confirmation_link = get_confirmation_link(config, token)?;
+    let (html_content, text_content) = confirmation_email_content(config, token)?;
+
+    email_client
+        .send_email(
+            &subscriber.email,
+            CONFIRMATION_EMAIL_SUBJECT,
+            &html_content,
+            &text_content,
+        )
+        .await?;
+    Ok(())
+}
 
+async fn send_already_subscribed_email(
+    email_client: &dyn EmailSender,
+    subscriber: &Subscriber,
+) -> Result<()> {
     email_client
         .send_email(
             &subscriber.email,
-            "Welcome!",
-            &format!(
-                "Welcome to our newsletter!<br />Click <a href=\"{}\">here</a> to confirm your subscription.",
-                confirmation_link
-            ),
-            &format!(
-                "Welcome to our newsletter!\nVisit {} to confirm your subscription.",
-                confirmation_link
-            ),
+            "You're already subscribed",
+            "Someone, hopefully you, tried to subscribe with this email.<br />You are already subscribed to our newsletter, there is nothing else to do.",
+            "Someone, hopefully you, tried to subscribe with this email.\nYou are already subscribed to our newsletter, there is nothing else to do.",
         )
         .await?;
     Ok(())
@@ -0,0 +1,170 @@
+use crate::{Config, Error, Result, model::ModelManager};
+use axum::{
+    Form,
+    extract::{FromRequest, Multipart, Query, Request, State},
+    http::header::CONTENT_TYPE,
+    response::{Html, IntoResponse, Response},
+};
+use htmlescape::encode_minimal;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::sync::Arc;
+use url::Url;
+
+#[derive(Debug, Deserialize)]
+pub struct UnsubscribeParams {
+    token: String,
+}
+
+#[tracing::instrument(skip(mm))]
+pub async fn unsubscribe_form(
+    State(mm): State<Arc<ModelManager>>,
+    Query(params): Query<UnsubscribeParams>,
+) -> Result<Response> {
+    let Some(email) = mm
+        .get_subscriber_by_unsubscribe_token(params.token.clone())
+        .await?
+    else {
+        return Ok(invalid_link_page());
+    };
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Unsubscribe</title>
+        </head>
+        <body>
+            <p>Do you want to stop receiving our newsletter at {email}?</p>
+            <form action="/subscriptions/unsubscribe" method="post">
+                <input type="hidden" name="token" value="{token}">
+                <button type="submit">Unsubscribe</button>
+            </form>
+        </body>
+        </html>
+        "#,
+        email = encode_minimal(&email),
+        token = encode_minimal(&params.token),
+    );
+
+    Ok((StatusCode::OK, Html(body)).into_response())
+}
+
+#[tracing::instrument(skip(mm))]
+pub async fn unsubscribe(
+    State(mm): State<Arc<ModelManager>>,
+    Form(form): Form<UnsubscribeParams>,
+) -> Result<Response> {
+    if !mm.unsubscribe(form.token).await? {
+        return Ok(invalid_link_page());
+    }
+
+    let body = r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Unsubscribed</title>
+        </head>
+        <body>
+            <p>You have been unsubscribed, you won't receive our newsletter anymore.</p>
+        </body>
+        </html>
+        "#;
+
+    Ok((StatusCode::OK, Html(body)).into_response())
+}
+
+/// The body of a one-click unsubscribe, sent as `multipart/form-data` as
+/// RFC 8058 asks, or url-encoded by some mail clients.
+#[derive(Debug, Deserialize)]
+pub struct OneClickForm {
+    #[serde(rename = "List-Unsubscribe")]
+    list_unsubscribe: String,
+}
+
+impl<S> FromRequest<S> for OneClickForm
+where
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self> {
+        let is_multipart = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .is_some_and(|content_type| content_type.starts_with("multipart/form-data"));
+        if !is_multipart {
+            let Form(form) = Form::from_request(request, state).await?;
+            return Ok(form);
+        }
+
+        let mut multipart = Multipart::from_request(request, state).await?;
+        while let Some(field) = multipart.next_field().await? {
+            if field.name() == Some("List-Unsubscribe") {
+                return Ok(Self {
+                    list_unsubscribe: field.text().await?,
+                });
+            }
+        }
+
+        Err(validator::ValidationError::new("INVALID_BODY")
+            .with_message("missing the `List-Unsubscribe` field".into())
+            .into())
+    }
+}
+
+/// RFC 8058 one-click unsubscribe, posted by mail clients on behalf of the
+/// recipient, without any confirmation page.
+#[tracing::instrument(skip(mm))]
+pub async fn unsubscribe_one_click(
+    State(mm): State<Arc<ModelManager>>,
+    Query(params): Query<UnsubscribeParams>,
+    form: OneClickForm,
+) -> Result<StatusCode> {
+    if form.list_unsubscribe != "One-Click" {
+        return Ok(StatusCode::BAD_REQUEST);
+    }
+
+    if !mm.unsubscribe(params.token).await? {
+        return Ok(StatusCode::NOT_FOUND);
+    }
+
+    Ok(StatusCode::OK)
+}
+
+pub fn get_unsubscribe_link(config: &Config, unsubscribe_token: &str) -> Result<Url> {
+    let mut unsubscribe_link = config.base_url.join("subscriptions/unsubscribe")?;
+    unsubscribe_link.set_query(Some(&format!("token={unsubscribe_token}")));
+
+    Ok(unsubscribe_link)
+}
+
+pub fn get_one_click_unsubscribe_link(config: &Config, unsubscribe_token: &str) -> Result<Url> {
+    let mut unsubscribe_link = config
+        .base_url
+        .join("subscriptions/unsubscribe/one-click")?;
+    unsubscribe_link.set_query(Some(&format!("token={unsubscribe_token}")));
+
+    Ok(unsubscribe_link)
+}
+
+fn invalid_link_page() -> Response {
+    let body = r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Unsubscribe</title>
+        </head>
+        <body>
+            <p>This unsubscribe link is invalid or has already been used.</p>
+        </body>
+        </html>
+        "#;
+
+    (StatusCode::NOT_FOUND, Html(body)).into_response()
+}
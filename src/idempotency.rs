@@ -0,0 +1,126 @@
+use crate::{
+    Error, Result,
+    config::IdempotencyConfig,
+    domain::IdempotencyKey,
+    model::{IdempotencyEndpoint, ModelManager, SavedHeader, SavedResponse},
+    state::AppState,
+};
+use axum::{
+    body::Body,
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::Response,
+};
+use std::time::Duration;
+use surrealdb::RecordId;
+use validator::ValidationError;
+
+const IDEMPOTENCY_KEY_HEADER: HeaderName = HeaderName::from_static("idempotency-key");
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+pub enum NextAction {
+    StartProcessing,
+    InProgress,
+    ReturnSavedResponse(Response),
+}
+
+/// Extract the optional `Idempotency-Key` header of a request.
+pub fn get_idempotency_key(headers: &HeaderMap) -> Result<Option<IdempotencyKey>> {
+    headers
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .map(|value| {
+            let value = value.to_str().map_err(|_| {
+                ValidationError::new("INVALID_IDEMPOTENCY_KEY")
+                    .with_message("idempotency key is not valid ASCII".into())
+            })?;
+            Ok(IdempotencyKey::try_from(value.to_string())?)
+        })
+        .transpose()
+}
+
+pub async fn try_processing(
+    mm: &ModelManager,
+    user_id: &RecordId,
+    endpoint: IdempotencyEndpoint,
+    key: &IdempotencyKey,
+    config: &IdempotencyConfig,
+) -> Result<NextAction> {
+    match mm
+        .try_insert_idempotency_key(user_id, endpoint, key, config)
+        .await?
+    {
+        None => Ok(NextAction::StartProcessing),
+        Some(record) => match record.response {
+            Some(saved) => Ok(NextAction::ReturnSavedResponse(into_response(saved)?)),
+            None => Ok(NextAction::InProgress),
+        },
+    }
+}
+
+/// Persist `response` for later retries and hand it back to be sent.
+pub async fn save_response(
+    mm: &ModelManager,
+    user_id: &RecordId,
+    endpoint: IdempotencyEndpoint,
+    key: &IdempotencyKey,
+    response: Response,
+) -> Result<Response> {
+    let (parts, body) = response.into_parts();
+    let body = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+
+    let saved = SavedResponse {
+        status_code: parts.status.as_u16(),
+        headers: parts
+            .headers
+            .iter()
+            .map(|(name, value)| SavedHeader {
+                name: name.to_string(),
+                value: value.as_bytes().to_vec(),
+            })
+            .collect(),
+        body: body.to_vec(),
+    };
+    mm.save_idempotent_response(user_id, endpoint, key, saved)
+        .await?;
+
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+/// Periodically delete the idempotency keys older than the configured TTL,
+/// until the application shuts down.
+pub async fn run_expiry_until_stopped(state: AppState) {
+    while !state.shutdown.is_cancelled() {
+        match state
+            .mm
+            .delete_expired_idempotency_keys(state.config.idempotency.key_ttl)
+            .await
+        {
+            Ok(deleted) => tracing::debug!("Deleted {deleted} expired idempotency keys"),
+            Err(err) => tracing::error!("Failed to delete expired idempotency keys: {err:?}"),
+        }
+
+        tokio::select! {
+            () = tokio::time::sleep(EXPIRY_INTERVAL) => {}
+            () = state.shutdown.cancelled() => {}
+        }
+    }
+
+    tracing::info!("Idempotency key expiry stopped");
+}
+
+fn into_response(saved: SavedResponse) -> Result<Response> {
+    let mut response = Response::new(Body::from(saved.body));
+    *response.status_mut() =
+        StatusCode::from_u16(saved.status_code).map_err(|err| Error::Custom(err.to_string()))?;
+
+    for header in saved.headers {
+        let name =
+            HeaderName::try_from(header.name).map_err(|err| Error::Custom(err.to_string()))?;
+        let value =
+            HeaderValue::try_from(header.value).map_err(|err| Error::Custom(err.to_string()))?;
+        response.headers_mut().append(name, value);
+    }
+
+    Ok(response)
+}
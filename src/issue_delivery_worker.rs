@@ -0,0 +1,238 @@
+use crate::{
+    Config, Result,
+    config::DeliveryConfig,
+    domain::SubscriberEmail,
+    email_client::{EmailHeader, EmailSender},
+    handlers::{
+        CONFIRMATION_EMAIL_SUBJECT, confirmation_email_content, get_one_click_unsubscribe_link,
+        get_unsubscribe_link,
+    },
+    model::{IssueContent, ModelManager},
+    state::AppState,
+};
+use rand::Rng;
+use std::time::Duration;
+
+/// How long a claimed task stays hidden from other workers.
+const TASK_LOCK_DURATION: Duration = Duration::from_secs(60 * 5);
+const EMPTY_QUEUE_POLL_INTERVAL: Duration = Duration::from_secs(10);
+const ERROR_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+pub enum ExecutionOutcome {
+    TaskCompleted,
+    EmptyQueue,
+}
+
+/// Delivers queued emails until `state.shutdown` is cancelled. A delivery
+/// in progress is finished first; the rest of the queue waits for the next run.
+pub async fn run_worker_until_stopped(state: AppState) {
+    while !state.shutdown.is_cancelled() {
+        let pause =
+            match try_execute_task(&state.mm, state.email_client.as_ref(), &state.config).await {
+                Ok(ExecutionOutcome::EmptyQueue) => EMPTY_QUEUE_POLL_INTERVAL,
+                Ok(ExecutionOutcome::TaskCompleted) => continue,
+                Err(err) => {
+                    tracing::error!("Failed to execute delivery task: {err:?}");
+                    ERROR_RETRY_INTERVAL
+                }
+            };
+
+        tokio::select! {
+            () = tokio::time::sleep(pause) => {}
+            () = state.shutdown.cancelled() => {}
+        }
+    }
+
+    tracing::info!("Delivery worker stopped");
+}
+
+#[tracing::instrument(
+    skip_all,
+    fields(task_id = tracing::field::Empty, subscriber_email = tracing::field::Empty),
+)]
+pub async fn try_execute_task(
+    mm: &ModelManager,
+    email_client: &dyn EmailSender,
+    config: &Config,
+) -> Result<ExecutionOutcome> {
+    let Some(task) = mm.claim_delivery_task(TASK_LOCK_DURATION).await? else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+
+    let span = tracing::Span::current();
+    span.record("task_id", tracing::field::display(&task.id));
+    span.record(
+        "subscriber_email",
+        tracing::field::display(&task.subscriber_email),
+    );
+
+    let message = match &task.issue {
+        Some(issue) => match &task.unsubscribe_token {
+            Some(unsubscribe_token) => issue_message(config, issue, unsubscribe_token)?,
+            None => {
+                tracing::info!("Skipping a subscriber who is no longer confirmed");
+                mm.delete_delivery_task(task.id).await?;
+                return Ok(ExecutionOutcome::TaskCompleted);
+            }
+        },
+        None => match &task.confirmation_token {
+            Some(confirmation_token) => confirmation_message(config, confirmation_token)?,
+            None => {
+                tracing::info!("Skipping a subscriber who is no longer pending");
+                mm.delete_delivery_task(task.id).await?;
+                return Ok(ExecutionOutcome::TaskCompleted);
+            }
+        },
+    };
+
+    let email = match SubscriberEmail::try_from(task.subscriber_email) {
+        Ok(email) => email,
+        Err(err) => {
+            tracing::error!("Skipping a subscriber with an invalid email: {err:?}");
+            mm.dead_letter_delivery_task(task.id, &err.to_string())
+                .await?;
+            return Ok(ExecutionOutcome::TaskCompleted);
+        }
+    };
+
+    match email_client
+        .send_email_with_headers(
+            &email,
+            &message.subject,
+            &message.html_content,
+            &message.text_content,
+            &message.headers,
+        )
+        .await
+    {
+        Ok(()) => mm.complete_delivery_task(task.id).await?,
+        Err(err) if err.is_retryable() && task.n_retries + 1 < config.delivery.max_attempts => {
+            let retry_after = backoff(&config.delivery, task.n_retries);
+            tracing::warn!("Failed to deliver email, retrying in {retry_after:?}: {err:?}");
+            mm.reschedule_delivery_task(task.id, retry_after, &err.to_string())
+                .await?;
+        }
+        Err(err) => {
+            tracing::error!("Failed to deliver email, giving up: {err:?}");
+            mm.dead_letter_delivery_task(task.id, &err.to_string())
+                .await?;
+        }
+    }
+
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+/// An email rendered from a delivery task.
+struct Message {
+    subject: String,
+    html_content: String,
+    text_content: String,
+    headers: Vec<EmailHeader>,
+}
+
+/// A newsletter issue, with the links to unsubscribe from the next ones.
+fn issue_message(
+    config: &Config,
+    issue: &IssueContent,
+    unsubscribe_token: &str,
+) -> Result<Message> {
+    let unsubscribe_link = get_unsubscribe_link(config, unsubscribe_token)?;
+
+    Ok(Message {
+        subject: issue.title.clone(),
+        html_content: format!(
+            "{}<p>Click <a href=\"{unsubscribe_link}\">here</a> to unsubscribe.</p>",
+            issue.html_content
+        ),
+        text_content: format!(
+            "{}\n\nVisit {unsubscribe_link} to unsubscribe.",
+            issue.text_content
+        ),
+        headers: list_unsubscribe_headers(config, unsubscribe_token)?.into(),
+    })
+}
+
+fn confirmation_message(config: &Config, confirmation_token: &str) -> Result<Message> {
+    let (html_content, text_content) = confirmation_email_content(config, confirmation_token)?;
+
+    Ok(Message {
+        subject: CONFIRMATION_EMAIL_SUBJECT.into(),
+        html_content,
+        text_content,
+        headers: Vec::new(),
+    })
+}
+
+/// RFC 2369 / RFC 8058 headers letting mail clients offer one-click unsubscribe.
+fn list_unsubscribe_headers(config: &Config, unsubscribe_token: &str) -> Result<[EmailHeader; 2]> {
+    let one_click_link = get_one_click_unsubscribe_link(config, unsubscribe_token)?;
+    let mailto = format!(
+        "mailto:{}?subject=unsubscribe",
+        config.email_client.sender_email.as_ref()
+    );
+
+    Ok([
+        EmailHeader::new(
+            "List-Unsubscribe",
+            format!("<{mailto}>, <{one_click_link}>"),
+        ),
+        EmailHeader::new("List-Unsubscribe-Post", "List-Unsubscribe=One-Click"),
+    ])
+}
+
+/// Delay before the retry following `n_retries` previous retries:
+/// `initial_backoff * 2^n_retries`, capped at `max_backoff`, with jitter.
+fn backoff(config: &DeliveryConfig, n_retries: u32) -> Duration {
+    let exponential = config
+        .initial_backoff
+        .saturating_mul(2u32.saturating_pow(n_retries))
+        .min(config.max_backoff);
+
+    let jitter = config.jitter.clamp(0.0, 1.0);
+    if jitter == 0.0 {
+        return exponential;
+    }
+    let factor = rand::rng().random_range(1.0 - jitter..=1.0 + jitter);
+    exponential.mul_f64(factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::backoff;
+    use crate::config::DeliveryConfig;
+    use std::time::Duration;
+
+    fn config(jitter: f64) -> DeliveryConfig {
+        DeliveryConfig {
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            jitter,
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_after_each_retry() {
+        let config = config(0.0);
+        assert_eq!(backoff(&config, 0), Duration::from_secs(1));
+        assert_eq!(backoff(&config, 1), Duration::from_secs(2));
+        assert_eq!(backoff(&config, 2), Duration::from_secs(4));
+        assert_eq!(backoff(&config, 5), Duration::from_secs(32));
+    }
+
+    #[test]
+    fn backoff_is_capped_at_max_backoff() {
+        let config = config(0.0);
+        assert_eq!(backoff(&config, 6), Duration::from_secs(60));
+        assert_eq!(backoff(&config, u32::MAX), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn backoff_jitter_stays_within_bounds() {
+        let config = config(0.5);
+        for _ in 0..100 {
+            let delay = backoff(&config, 2);
+            assert!(delay >= Duration::from_secs(2) && delay <= Duration::from_secs(6));
+        }
+    }
+}
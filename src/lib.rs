@@ -1,14 +1,24 @@
+mod authentication;
+pub mod cli;
 mod config;
 mod domain;
 mod email_client;
 mod errors;
 mod handlers;
+mod idempotency;
+mod issue_delivery_worker;
 mod model;
 mod session_state;
+mod shutdown;
 mod startup;
 mod state;
+mod telemetry;
 
-pub use config::Config;
+pub use config::{Config, DatabaseConfig, EmailProviderConfig, IdempotencyConfig, TracingConfig};
 pub use errors::{Error, Result};
-pub use startup::init;
+pub use issue_delivery_worker::{ExecutionOutcome, run_worker_until_stopped, try_execute_task};
+pub use model::ModelManager;
+pub use shutdown::{serve_until_stopped, shutdown_signal};
+pub use startup::{init, metrics_router, spawn_metrics_server};
 pub use state::AppState;
+pub use telemetry::{init_tracer_provider, otel_layer};
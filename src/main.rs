@@ -1,32 +1,73 @@
-use subscriptions::{Config, Error};
+use clap::Parser;
+use subscriptions::{
+    Config, DatabaseConfig, Error, ModelManager, TracingConfig,
+    cli::{Cli, Command},
+};
 use tokio::net::TcpListener;
 use tracing_subscriber::prelude::*;
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
+    let cli = Cli::parse();
+
     // Load environment variables from .env file if exists
     dotenvy::dotenv_override().ok();
 
-    // Initialize tracing
+    // Initialize tracing, keeping stdout for the output of commands and
+    // exporting spans when a collector is configured
+    let tracer_provider = subscriptions::init_tracer_provider(&TracingConfig::load()?)?;
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::from_default_env())
-        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(tracer_provider.as_ref().map(subscriptions::otel_layer))
         .init();
 
-    // Initialize configuration
-    let config = Config::load()?;
+    let result = match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(Config::load()?).await,
+        // Only the database settings, so admins and migrations can be managed
+        // from a shell without the settings of the server
+        Command::User(command) => {
+            let mm = ModelManager::new(DatabaseConfig::load()?);
+            mm.prepare_schema().await?;
+            command
+                .run(&mm, &mut std::io::stdin().lock(), &mut std::io::stdout())
+                .await
+        }
+        Command::Migrate(command) => {
+            let mm = ModelManager::new(DatabaseConfig::load()?);
+            command.run(&mm, &mut std::io::stdout()).await
+        }
+    };
+
+    // Send the spans still buffered before exiting
+    if let Some(provider) = tracer_provider
+        && let Err(err) = provider.shutdown()
+    {
+        tracing::warn!("Failed to flush traces: {err:?}");
+    }
+
+    result
+}
 
+async fn serve(config: Config) -> Result<(), Error> {
     // Initialize application
-    let (router, _) = subscriptions::init(config.clone()).await?;
+    let (router, state) = subscriptions::init(config.clone()).await?;
 
     // Bind address
     let listener = TcpListener::bind((config.host.clone(), config.port)).await?;
 
+    // Serve metrics apart from the application, if asked to
+    if let Some(metrics_port) = config.metrics.port {
+        let metrics_listener = TcpListener::bind((config.host.clone(), metrics_port)).await?;
+        tracing::info!(
+            "Serving metrics on: http://{}:{metrics_port}/metrics",
+            config.host
+        );
+        subscriptions::spawn_metrics_server(metrics_listener, &state);
+    }
+
     // Start server
     tracing::info!("Start listening on: http://{}:{}", config.host, config.port);
-    axum::serve(listener, router).await?;
-
-    tracing::info!("Server gracefully shutdown");
-
-    Ok(())
+    subscriptions::serve_until_stopped(listener, router, state, subscriptions::shutdown_signal())
+        .await
 }
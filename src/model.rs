@@ -1,10 +1,22 @@
-use crate::{Error, Result, config::DatabaseConfig, domain, handlers::Credentials};
-use include_dir::include_dir;
+use crate::{
+    Error, Result,
+    config::{DatabaseConfig, IdempotencyConfig},
+    domain::{self, ApiScope, IdempotencyKey, NewApiToken, NewPassword, TotpSecret},
+    handlers::Credentials,
+    session_state::{SESSIONS_TABLE, TypedSession},
+};
+use include_dir::{Dir, include_dir};
 use secrecy::ExposeSecret;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
 use surrealdb::{RecordId, Surreal, engine::any::Any, opt::auth::Database};
 use surrealdb_migrations::MigrationRunner;
 use tokio::sync::OnceCell;
+use tower_sessions::session::Record;
+use utoipa::ToSchema;
+
+/// Schema definitions and migrations, embedded in the binary.
+static MIGRATIONS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/surrealdb");
 
 #[derive(Debug, Clone)]
 pub struct ModelManager {
@@ -12,117 +24,1583 @@ pub struct ModelManager {
     db: OnceCell<Surreal<Any>>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct ConfirmedSubscriber {
-    pub email: String,
-}
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SubscribeOutcome {
+    /// The subscriber has to confirm with the newly issued token.
+    Pending,
+    /// The email is already on the list, no token was issued.
+    AlreadyConfirmed,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ConfirmationOutcome {
+    Confirmed,
+    /// The link was already used, confirming again is a no-op.
+    AlreadyConfirmed,
+    /// The token was issued longer ago than the configured TTL.
+    Expired,
+    /// The token is unknown or was replaced by a newer one.
+    InvalidToken,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SubscriptionStatus {
+    Pending,
+    Confirmed,
+    Unsubscribed,
+}
+
+impl SubscriptionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "PENDING",
+            Self::Confirmed => "CONFIRMED",
+            Self::Unsubscribed => "UNSUBSCRIBED",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    NewestFirst,
+    OldestFirst,
+}
+
+#[derive(Debug)]
+pub struct SubscriberQuery {
+    pub status: Option<SubscriptionStatus>,
+    /// Case insensitive prefix of the email or the name.
+    pub search: Option<String>,
+    pub sort: SortOrder,
+    /// Only return subscribers sorted after this one.
+    pub after: Option<SubscriberCursor>,
+    pub limit: usize,
+}
+
+/// Position of a subscriber in the list, as `created_at` and `id` keep the
+/// order stable while subscribers are added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriberCursor {
+    pub created_at: String,
+    pub id: RecordId,
+}
+
+#[derive(Debug)]
+pub struct SubscribersPage {
+    pub subscribers: Vec<SubscriberSummary>,
+    pub next: Option<SubscriberCursor>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubscriberSummary {
+    pub id: RecordId,
+    pub email: String,
+    pub name: String,
+    pub status: SubscriptionStatus,
+    #[serde(rename = "created_at_text")]
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatusCount {
+    pub status: SubscriptionStatus,
+    pub count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenHistoryEntry {
+    #[serde(rename = "created_at_text")]
+    pub created_at: String,
+    /// Only the current token can confirm the subscription.
+    pub current: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveryLogEntry {
+    pub title: String,
+    pub outcome: String,
+    pub error: Option<String>,
+    #[serde(rename = "attempted_at_text")]
+    pub attempted_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveryTask {
+    pub id: RecordId,
+    pub n_retries: u32,
+    pub subscriber_email: String,
+    /// `None` for the confirmation email of an imported subscriber.
+    pub issue: Option<IssueContent>,
+    /// `None` once the subscriber is no longer confirmed.
+    pub unsubscribe_token: Option<String>,
+    /// The token of the confirmation link, `None` once the subscriber is no
+    /// longer pending.
+    pub confirmation_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IssueContent {
+    pub title: String,
+    pub text_content: String,
+    pub html_content: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeadLetter {
+    pub id: RecordId,
+    pub subscriber_email: String,
+    pub title: String,
+    pub attempts: u32,
+    pub last_error: String,
+    pub failed_at: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct MigrationStatus {
+    /// Applied migrations, in execution order.
+    pub applied: Vec<String>,
+    /// Embedded migrations not applied yet, in the order they would run.
+    pub pending: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserSummary {
+    pub username: String,
+    pub created_at: String,
+}
+
+/// The second factor of a user who enrolled an authenticator app.
+#[derive(Debug)]
+pub struct TwoFactor {
+    pub secret: TotpSecret,
+    /// Time step of the last accepted code, older ones can't be used again.
+    pub last_step: Option<u64>,
+    pub recovery_codes_left: usize,
+    /// Too many wrong codes were given, no code is accepted for a while.
+    pub locked: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiTokenSummary {
+    pub id: RecordId,
+    pub name: String,
+    pub owner: String,
+    pub scopes: Vec<ApiScope>,
+    pub created_at: String,
+    pub expires_at: Option<String>,
+    pub last_used_at: Option<String>,
+    pub revoked: bool,
+    pub expired: bool,
+}
+
+/// The user an API token acts for, and what it is allowed to do.
+#[derive(Debug, Deserialize)]
+pub struct ApiClient {
+    pub user: RecordId,
+    pub username: String,
+    pub scopes: Vec<ApiScope>,
+}
+
+/// Endpoint an idempotency key was sent to. Each renders its own response, so
+/// the same key sent to two of them is unrelated.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdempotencyEndpoint {
+    Newsletter,
+    AdminNewsletters,
+    ApiNewsletters,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdempotencyRecord {
+    pub response: Option<SavedResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedResponse {
+    pub status_code: u16,
+    pub headers: Vec<SavedHeader>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedHeader {
+    pub name: String,
+    pub value: Vec<u8>,
+}
+
+impl ModelManager {
+    pub fn new(config: DatabaseConfig) -> Self {
+        Self {
+            config,
+            db: OnceCell::new(),
+        }
+    }
+
+    pub async fn db(&self) -> Result<&Surreal<Any>> {
+        self.db.get_or_try_init(async || self.connect().await).await
+    }
+
+    /// Applies pending migrations when `auto_migrate` is on, then makes sure
+    /// none is left, so a stale schema is caught before serving any request.
+    pub async fn prepare_schema(&self) -> Result<()> {
+        if self.config.auto_migrate {
+            self.migrate_up().await?;
+        }
+
+        let status = self.migration_status().await?;
+        if !status.pending.is_empty() {
+            return Err(Error::Migrations(format!(
+                "The database schema is behind, pending migrations: {}",
+                status.pending.join(", ")
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Applies the schema definitions and every pending migration.
+    pub async fn migrate_up(&self) -> Result<()> {
+        MigrationRunner::new(self.db().await?)
+            .load_files(&MIGRATIONS_DIR)
+            .up()
+            .await
+            .map_err(|e| Error::Migrations(e.to_string()))
+    }
+
+    /// Reverts the last applied migration, or every migration applied after
+    /// `to` when given.
+    pub async fn migrate_down(&self, to: Option<&str>) -> Result<()> {
+        let runner = MigrationRunner::new(self.db().await?).load_files(&MIGRATIONS_DIR);
+        match to {
+            Some(name) => runner.down_to(name).await,
+            None => runner.down_single().await,
+        }
+        .map_err(|e| Error::Migrations(e.to_string()))
+    }
+
+    pub async fn migration_status(&self) -> Result<MigrationStatus> {
+        let applied = MigrationRunner::new(self.db().await?)
+            .load_files(&MIGRATIONS_DIR)
+            .list()
+            .await
+            .map_err(|e| Error::Migrations(e.to_string()))?
+            .into_iter()
+            .map(|migration| migration.script_name)
+            .collect::<Vec<_>>();
+
+        let mut pending = MIGRATIONS_DIR
+            .get_dir("migrations")
+            .into_iter()
+            .flat_map(|dir| dir.files())
+            .filter(|file| file.path().extension().is_some_and(|ext| ext == "surql"))
+            .filter_map(|file| file.path().file_stem()?.to_str())
+            .filter(|name| !name.ends_with(".down"))
+            .filter(|name| !applied.iter().any(|applied| applied == name))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        pending.sort();
+
+        Ok(MigrationStatus { applied, pending })
+    }
+
+    /// Creates a pending subscriber. A known email keeps its subscription:
+    /// pending and unsubscribed subscribers get a fresh confirmation token
+    /// (starting a new double opt-in), confirmed ones are left untouched.
+    /// The name given with a known email only replaces the stored one once
+    /// the new token is confirmed.
+    pub async fn create_subscriber(
+        &self,
+        subscriber: &domain::Subscriber,
+        token: &str,
+        unsubscribe_token: &str,
+    ) -> Result<SubscribeOutcome> {
+        let outcome = match self
+            .upsert_subscriber(subscriber, token, unsubscribe_token)
+            .await?
+        {
+            Some(outcome) => Some(outcome),
+            // A concurrent request created the same email first, failing the
+            // unique index; the retry updates that subscriber instead
+            None => {
+                self.upsert_subscriber(subscriber, token, unsubscribe_token)
+                    .await?
+            }
+        };
+
+        outcome.ok_or(Error::Custom("Failed to create subscriber".into()))
+    }
+
+    /// Returns `None` if the email was created concurrently, failing the
+    /// `unique_email` index.
+    async fn upsert_subscriber(
+        &self,
+        subscriber: &domain::Subscriber,
+        token: &str,
+        unsubscribe_token: &str,
+    ) -> Result<Option<SubscribeOutcome>> {
+        let mut response = self
+            .db()
+            .await?
+            .query(
+                r#"
+                BEGIN TRANSACTION;
+                LET $existing = (
+                    SELECT id, status
+                    FROM ONLY subscriptions
+                    WHERE email = $email
+                    LIMIT 1
+                );
+                RETURN IF $existing.status = 'CONFIRMED' {
+                    'ALREADY_CONFIRMED'
+                } ELSE {
+                    LET $subscription_token = (CREATE ONLY subscription_tokens CONTENT {
+                        token: $token_val,
+                        name: IF $existing { $name } ELSE { NONE }
+                    });
+                    LET $subscription = IF $existing {
+                        (UPDATE ONLY $existing.id SET status = 'PENDING', token = $subscription_token.id)
+                    } ELSE {
+                        (CREATE ONLY subscriptions CONTENT {
+                            email: $email,
+                            name: $name,
+                            token: $subscription_token.id,
+                            unsubscribe_token: $unsubscribe_token
+                        })
+                    };
+                    UPDATE $subscription_token.id SET subscriber = $subscription.id;
+                    'PENDING'
+                };
+                COMMIT TRANSACTION;
+            "#,
+            )
+            .bind(("token_val", token.to_string()))
+            .bind(("unsubscribe_token", unsubscribe_token.to_string()))
+            .bind(("email", subscriber.email.as_ref().to_string()))
+            .bind(("name", subscriber.name.as_ref().to_string()))
+            .timed("create_subscriber")
+            .await?;
+
+        // The failing statement reports the violation, the others only that
+        // the transaction failed
+        let errors = response.take_errors();
+        if errors
+            .values()
+            .any(|err| err.to_string().contains("`unique_email`"))
+        {
+            return Ok(None);
+        }
+        if let Some((_, err)) = errors.into_iter().min_by_key(|(index, _)| *index) {
+            return Err(err.into());
+        }
+
+        Ok(response.take(0)?)
+    }
+
+    /// Creates a subscriber with `status`, leaving known emails untouched.
+    /// Pending subscribers get their confirmation email through the delivery
+    /// queue.
+    ///
+    /// Returns `false` if the email was already known.
+    pub async fn import_subscriber(
+        &self,
+        subscriber: &domain::Subscriber,
+        status: SubscriptionStatus,
+        token: &str,
+        unsubscribe_token: &str,
+    ) -> Result<bool> {
+        let created: Option<bool> = self
+            .db()
+            .await?
+            .query(
+                r#"
+                BEGIN TRANSACTION;
+                RETURN IF (SELECT VALUE id FROM ONLY subscriptions WHERE email = $email LIMIT 1) {
+                    false
+                } ELSE {
+                    LET $subscription_token = (CREATE ONLY subscription_tokens CONTENT { token: $token_val });
+                    LET $subscription = (CREATE ONLY subscriptions CONTENT {
+                        email: $email,
+                        name: $name,
+                        status: $status,
+                        token: $subscription_token.id,
+                        unsubscribe_token: $unsubscribe_token
+                    });
+                    UPDATE $subscription_token.id SET subscriber = $subscription.id;
+                    IF $status = 'PENDING' {
+                        CREATE issue_delivery_queue CONTENT { subscriber_email: $email };
+                    };
+                    true
+                };
+                COMMIT TRANSACTION;
+            "#,
+            )
+            .bind(("token_val", token.to_string()))
+            .bind(("unsubscribe_token", unsubscribe_token.to_string()))
+            .bind(("email", subscriber.email.as_ref().to_string()))
+            .bind(("name", subscriber.name.as_ref().to_string()))
+            .bind(("status", status))
+            .timed("import_subscriber")
+            .await?
+            .take(0)?;
+
+        created.ok_or(Error::Custom("Failed to import subscriber".into()))
+    }
+
+    /// Confirms the pending subscriber whose current token is `token`, unless
+    /// it was issued more than `token_ttl` ago. Unsubscribed subscribers can't
+    /// be confirmed again with an old link.
+    pub async fn confirm_subscriber(
+        &self,
+        token: String,
+        token_ttl: Duration,
+    ) -> Result<ConfirmationOutcome> {
+        let outcome: Option<ConfirmationOutcome> = self
+            .db()
+            .await?
+            .query(
+                r#"
+                BEGIN TRANSACTION;
+                LET $subscription_token = (
+                    SELECT id, name, created_at
+                    FROM ONLY subscription_tokens
+                    WHERE token = $token_val
+                    LIMIT 1
+                );
+                LET $subscription = (
+                    SELECT id, status
+                    FROM ONLY subscriptions
+                    WHERE token = $subscription_token.id AND status != 'UNSUBSCRIBED'
+                    LIMIT 1
+                );
+                RETURN IF !$subscription {
+                    'INVALID_TOKEN'
+                } ELSE IF $subscription.status = 'CONFIRMED' {
+                    'ALREADY_CONFIRMED'
+                } ELSE IF $subscription_token.created_at + duration::from::millis($token_ttl) < time::now() {
+                    'EXPIRED'
+                } ELSE {
+                    UPDATE $subscription.id SET status = 'CONFIRMED', name = $subscription_token.name ?? name;
+                    'CONFIRMED'
+                };
+                COMMIT TRANSACTION;
+            "#,
+            )
+            .bind(("token_val", token))
+            .bind(("token_ttl", token_ttl.as_millis() as u64))
+            .timed("confirm_subscriber")
+            .await?
+            .take(0)?;
+
+        outcome.ok_or(Error::Custom("Failed to confirm subscriber".into()))
+    }
+
+    /// Returns the email of the subscriber owning `unsubscribe_token`, unless
+    /// they already unsubscribed.
+    pub async fn get_subscriber_by_unsubscribe_token(
+        &self,
+        unsubscribe_token: String,
+    ) -> Result<Option<String>> {
+        Ok(self
+            .db()
+            .await?
+            .query(
+                r#"
+                SELECT VALUE email
+                FROM ONLY subscriptions
+                WHERE unsubscribe_token = $unsubscribe_token AND status != 'UNSUBSCRIBED'
+                LIMIT 1;
+            "#,
+            )
+            .bind(("unsubscribe_token", unsubscribe_token))
+            .timed("get_subscriber_by_unsubscribe_token")
+            .await?
+            .take(0)?)
+    }
+
+    /// Returns `false` if no subscriber owns `unsubscribe_token`.
+    pub async fn unsubscribe(&self, unsubscribe_token: String) -> Result<bool> {
+        let updated: Vec<RecordId> = self
+            .db()
+            .await?
+            .query(
+                r#"
+                UPDATE subscriptions
+                SET status = 'UNSUBSCRIBED'
+                WHERE unsubscribe_token = $unsubscribe_token
+                RETURN VALUE id;
+            "#,
+            )
+            .bind(("unsubscribe_token", unsubscribe_token))
+            .timed("unsubscribe")
+            .await?
+            .take(0)?;
+
+        Ok(!updated.is_empty())
+    }
+
+    /// One page of subscribers matching `query`, sorted by `created_at` then `id`.
+    pub async fn list_subscribers(&self, query: &SubscriberQuery) -> Result<SubscribersPage> {
+        let (comparison, order) = match query.sort {
+            SortOrder::NewestFirst => ("<", "DESC"),
+            SortOrder::OldestFirst => (">", "ASC"),
+        };
+
+        let mut conditions = Vec::new();
+        if query.status.is_some() {
+            conditions.push("status = $status".to_string());
+        }
+        if query.search.is_some() {
+            conditions.push(
+                "(string::starts_with(string::lowercase(email), $search) OR string::starts_with(string::lowercase(name), $search))"
+                    .to_string(),
+            );
+        }
+        if query.after.is_some() {
+            conditions.push(format!(
+                "(created_at {comparison} <datetime> $cursor_created_at OR (created_at = <datetime> $cursor_created_at AND id {comparison} $cursor_id))"
+            ));
+        }
+        let filter = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+
+        let (cursor_created_at, cursor_id) = query
+            .after
+            .clone()
+            .map(|cursor| (cursor.created_at, cursor.id))
+            .unzip();
+
+        // Fetch an extra row to find out whether there is a next page.
+        let mut subscribers: Vec<SubscriberSummary> = self
+            .db()
+            .await?
+            .query(format!(
+                r#"
+                SELECT id, email, name, status, created_at, <string> created_at AS created_at_text
+                FROM subscriptions
+                {filter}
+                ORDER BY created_at {order}, id {order}
+                LIMIT $limit;
+            "#
+            ))
+            .bind(("status", query.status))
+            .bind((
+                "search",
+                query.search.as_ref().map(|search| search.to_lowercase()),
+            ))
+            .bind(("cursor_created_at", cursor_created_at))
+            .bind(("cursor_id", cursor_id))
+            .bind(("limit", query.limit + 1))
+            .timed("list_subscribers")
+            .await?
+            .take(0)?;
+
+        let next = if subscribers.len() > query.limit {
+            subscribers.truncate(query.limit);
+            subscribers.last().map(|last| SubscriberCursor {
+                created_at: last.created_at.clone(),
+                id: last.id.clone(),
+            })
+        } else {
+            None
+        };
+
+        Ok(SubscribersPage { subscribers, next })
+    }
+
+    /// Number of subscribers per status, leaving out statuses nobody has.
+    pub async fn count_subscribers_by_status(&self) -> Result<Vec<StatusCount>> {
+        Ok(self
+            .db()
+            .await?
+            .query(
+                r#"
+                SELECT status, count() AS count
+                FROM subscriptions
+                GROUP BY status;
+            "#,
+            )
+            .timed("count_subscribers_by_status")
+            .await?
+            .take(0)?)
+    }
+
+    pub async fn get_subscriber(&self, id: RecordId) -> Result<Option<SubscriberSummary>> {
+        Ok(self
+            .db()
+            .await?
+            .query(
+                r#"
+                SELECT id, email, name, status, <string> created_at AS created_at_text
+                FROM ONLY $id;
+            "#,
+            )
+            .bind(("id", id))
+            .timed("get_subscriber")
+            .await?
+            .take(0)?)
+    }
+
+    /// Every confirmation token issued to the subscriber, newest first.
+    pub async fn get_subscriber_tokens(&self, id: RecordId) -> Result<Vec<TokenHistoryEntry>> {
+        Ok(self
+            .db()
+            .await?
+            .query(
+                r#"
+                SELECT
+                    created_at,
+                    <string> created_at AS created_at_text,
+                    id = $id.token AS current
+                FROM subscription_tokens
+                WHERE subscriber = $id
+                ORDER BY created_at DESC;
+            "#,
+            )
+            .bind(("id", id))
+            .timed("get_subscriber_tokens")
+            .await?
+            .take(0)?)
+    }
+
+    /// The latest delivery attempts of newsletter issues and confirmation
+    /// emails to `email`, newest first.
+    pub async fn get_subscriber_deliveries(&self, email: &str) -> Result<Vec<DeliveryLogEntry>> {
+        Ok(self
+            .db()
+            .await?
+            .query(
+                r#"
+                SELECT
+                    newsletter_issue.title ?? 'Confirmation email' AS title,
+                    outcome,
+                    error,
+                    attempted_at,
+                    <string> attempted_at AS attempted_at_text
+                FROM issue_delivery_log
+                WHERE subscriber_email = $email
+                ORDER BY attempted_at DESC
+                LIMIT 100;
+            "#,
+            )
+            .bind(("email", email.to_string()))
+            .timed("get_subscriber_deliveries")
+            .await?
+            .take(0)?)
+    }
+
+    /// Store a newsletter issue and queue one delivery task per confirmed subscriber.
+    ///
+    /// Returns the number of queued deliveries.
+    pub async fn enqueue_newsletter_issue(
+        &self,
+        title: &str,
+        text_content: &str,
+        html_content: &str,
+    ) -> Result<usize> {
+        Ok(self
+            .db()
+            .await?
+            .query(
+                r#"
+                BEGIN TRANSACTION;
+                LET $issue = (CREATE ONLY newsletter_issues CONTENT {
+                    title: $title,
+                    text_content: $text_content,
+                    html_content: $html_content
+                });
+                LET $emails = (SELECT VALUE email FROM subscriptions WHERE status = 'CONFIRMED');
+                FOR $email IN $emails {
+                    CREATE issue_delivery_queue CONTENT {
+                        newsletter_issue: $issue.id,
+                        subscriber_email: $email
+                    };
+                };
+                RETURN array::len($emails);
+                COMMIT TRANSACTION;
+            "#,
+            )
+            .bind(("title", title.to_string()))
+            .bind(("text_content", text_content.to_string()))
+            .bind(("html_content", html_content.to_string()))
+            .timed("enqueue_newsletter_issue")
+            .await?
+            .take::<Option<usize>>(0)?
+            .unwrap_or_default())
+    }
+
+    /// Lock the next due delivery task for `lock_for` and return it.
+    ///
+    /// A task whose lock expired (e.g. the process died while sending it) is
+    /// handed out again.
+    pub async fn claim_delivery_task(&self, lock_for: Duration) -> Result<Option<DeliveryTask>> {
+        Ok(self
+            .db()
+            .await?
+            .query(
+                r#"
+                BEGIN TRANSACTION;
+                LET $task = (
+                    SELECT id
+                    FROM ONLY issue_delivery_queue
+                    WHERE (locked_until = NONE OR locked_until < time::now())
+                    AND (execute_after = NONE OR execute_after <= time::now())
+                    LIMIT 1
+                );
+                IF $task != NONE {
+                    UPDATE $task.id SET locked_until = time::now() + duration::from::millis($lock_for);
+                };
+                RETURN IF $task != NONE {
+                    (
+                        SELECT
+                            id,
+                            n_retries ?? 0 AS n_retries,
+                            subscriber_email,
+                            (
+                                SELECT VALUE unsubscribe_token
+                                FROM ONLY subscriptions
+                                WHERE email = $parent.subscriber_email AND status = 'CONFIRMED'
+                                LIMIT 1
+                            ) AS unsubscribe_token,
+                            (
+                                SELECT VALUE token.token
+                                FROM ONLY subscriptions
+                                WHERE email = $parent.subscriber_email AND status = 'PENDING'
+                                LIMIT 1
+                            ) AS confirmation_token,
+                            newsletter_issue.{title, text_content, html_content} AS issue
+                        FROM ONLY $task.id
+                    )
+                };
+                COMMIT TRANSACTION;
+            "#,
+            )
+            .bind(("lock_for", lock_for.as_millis() as u64))
+            .timed("claim_delivery_task")
+            .await?
+            .take(0)?)
+    }
+
+    pub async fn delete_delivery_task(&self, id: RecordId) -> Result<()> {
+        self.db()
+            .await?
+            .query(r#"DELETE $id"#)
+            .bind(("id", id))
+            .timed("delete_delivery_task")
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    /// Remove a delivered task from the queue, logging the delivery.
+    pub async fn complete_delivery_task(&self, id: RecordId) -> Result<()> {
+        self.db()
+            .await?
+            .query(
+                r#"
+                BEGIN TRANSACTION;
+                LET $task = (SELECT * FROM ONLY $id);
+                CREATE issue_delivery_log CONTENT {
+                    newsletter_issue: $task.newsletter_issue,
+                    subscriber_email: $task.subscriber_email,
+                    outcome: 'DELIVERED'
+                };
+                DELETE $id;
+                COMMIT TRANSACTION;
+            "#,
+            )
+            .bind(("id", id))
+            .timed("complete_delivery_task")
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    /// Release a failed delivery task so it is attempted again after `retry_after`.
+    pub async fn reschedule_delivery_task(
+        &self,
+        id: RecordId,
+        retry_after: Duration,
+        error: &str,
+    ) -> Result<()> {
+        self.db()
+            .await?
+            .query(
+                r#"
+                BEGIN TRANSACTION;
+                LET $task = (UPDATE ONLY $id SET
+                    n_retries = (n_retries ?? 0) + 1,
+                    execute_after = time::now() + duration::from::millis($retry_after),
+                    locked_until = NONE,
+                    last_error = $error);
+                CREATE issue_delivery_log CONTENT {
+                    newsletter_issue: $task.newsletter_issue,
+                    subscriber_email: $task.subscriber_email,
+                    outcome: 'FAILED',
+                    error: $error
+                };
+                COMMIT TRANSACTION;
+            "#,
+            )
+            .bind(("id", id))
+            .bind(("retry_after", retry_after.as_millis() as u64))
+            .bind(("error", error.to_string()))
+            .timed("reschedule_delivery_task")
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    /// Move a delivery task that won't be attempted anymore to the dead letters.
+    pub async fn dead_letter_delivery_task(&self, id: RecordId, error: &str) -> Result<()> {
+        self.db()
+            .await?
+            .query(
+                r#"
+                BEGIN TRANSACTION;
+                LET $task = (SELECT * FROM ONLY $id);
+                CREATE issue_delivery_dead_letters CONTENT {
+                    newsletter_issue: $task.newsletter_issue,
+                    subscriber_email: $task.subscriber_email,
+                    attempts: ($task.n_retries ?? 0) + 1,
+                    last_error: $error
+                };
+                CREATE issue_delivery_log CONTENT {
+                    newsletter_issue: $task.newsletter_issue,
+                    subscriber_email: $task.subscriber_email,
+                    outcome: 'DEAD_LETTERED',
+                    error: $error
+                };
+                DELETE $id;
+                COMMIT TRANSACTION;
+            "#,
+            )
+            .bind(("id", id))
+            .bind(("error", error.to_string()))
+            .timed("dead_letter_delivery_task")
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    pub async fn get_dead_letters(&self) -> Result<Vec<DeadLetter>> {
+        Ok(self
+            .db()
+            .await?
+            .query(
+                r#"
+                SELECT
+                    id,
+                    subscriber_email,
+                    newsletter_issue.title ?? 'Confirmation email' AS title,
+                    attempts,
+                    last_error,
+                    <string> failed_at AS failed_at
+                FROM issue_delivery_dead_letters
+                ORDER BY failed_at DESC;
+            "#,
+            )
+            .timed("get_dead_letters")
+            .await?
+            .take(0)?)
+    }
+
+    /// Queue a dead-lettered delivery again, with a fresh retry budget.
+    ///
+    /// Returns `false` if no such dead letter exists.
+    pub async fn replay_dead_letter(&self, id: RecordId) -> Result<bool> {
+        Ok(self
+            .db()
+            .await?
+            .query(
+                r#"
+                BEGIN TRANSACTION;
+                LET $letter = (SELECT * FROM ONLY $id);
+                IF $letter != NONE {
+                    CREATE issue_delivery_queue CONTENT {
+                        newsletter_issue: $letter.newsletter_issue,
+                        subscriber_email: $letter.subscriber_email
+                    };
+                    DELETE $id;
+                };
+                RETURN $letter != NONE;
+                COMMIT TRANSACTION;
+            "#,
+            )
+            .bind(("id", id))
+            .timed("replay_dead_letter")
+            .await?
+            .take::<Option<bool>>(0)?
+            .unwrap_or_default())
+    }
+
+    pub async fn validate_credientials(&self, credentials: Credentials) -> Result<RecordId> {
+        #[derive(Debug, Deserialize)]
+        struct QueryResult {
+            id: RecordId,
+        }
+        let result = self
+            .db()
+            .await?
+            .query(
+                r#"
+                SELECT id
+                FROM ONLY users
+                WHERE username = $username AND crypto::argon2::compare(password, $password);
+                "#,
+            )
+            .bind(("username", credentials.username))
+            .bind(("password", credentials.password.expose_secret().to_string()))
+            .timed("validate_credientials")
+            .await?
+            .take::<Option<QueryResult>>(0)?
+            .ok_or(Error::Custom("User not found!".into()))?;
+        Ok(result.id)
+    }
+
+    pub async fn get_username(&self, id: RecordId) -> Result<String> {
+        self.db()
+            .await?
+            .query(r#"SELECT VALUE username FROM ONLY $recordId"#)
+            .bind(("recordId", id))
+            .timed("get_username")
+            .await?
+            .take::<Option<String>>(0)?
+            .ok_or(Error::Custom("User with this id don't exists".into()))
+    }
+
+    pub async fn change_password(&self, id: RecordId, password: &NewPassword) -> Result<()> {
+        self.db()
+            .await?
+            .query(r#"UPDATE $recordId SET password = crypto::argon2::generate($password)"#)
+            .bind(("recordId", id))
+            .bind(("password", password.expose_secret().to_string()))
+            .timed("change_password")
+            .await?
+            .check()?;
+
+        Ok(())
+    }
 
-impl ModelManager {
-    pub fn new(config: DatabaseConfig) -> Self {
-        Self {
-            config,
-            db: OnceCell::new(),
+    /// Returns `None` if the user didn't enable two-factor authentication.
+    pub async fn get_two_factor(&self, id: &RecordId) -> Result<Option<TwoFactor>> {
+        #[derive(Debug, Deserialize)]
+        struct QueryResult {
+            totp_secret: String,
+            totp_last_step: Option<u64>,
+            recovery_codes_left: usize,
+            locked: bool,
         }
-    }
+        let result = self
+            .db()
+            .await?
+            .query(
+                r#"
+                SELECT
+                    totp_secret,
+                    totp_last_step,
+                    array::len(recovery_codes ?? []) AS recovery_codes_left,
+                    locked_until != NONE AND locked_until > time::now() AS locked
+                FROM ONLY $recordId
+                WHERE totp_secret != NONE
+            "#,
+            )
+            .bind(("recordId", id.clone()))
+            .timed("get_two_factor")
+            .await?
+            .take::<Option<QueryResult>>(0)?;
 
-    pub async fn db(&self) -> Result<&Surreal<Any>> {
-        self.db.get_or_try_init(async || self.connect().await).await
+        Ok(result.map(|result| TwoFactor {
+            secret: TotpSecret::from_base32(result.totp_secret),
+            last_step: result.totp_last_step,
+            recovery_codes_left: result.recovery_codes_left,
+            locked: result.locked,
+        }))
     }
 
-    pub async fn create_subscriber(
+    /// Enables two-factor authentication with the `secret` a first code was
+    /// verified at `step` against, replacing any previous recovery codes.
+    pub async fn enable_two_factor(
         &self,
-        subscriber: &domain::Subscriber,
-        token: &str,
+        id: &RecordId,
+        secret: &TotpSecret,
+        step: u64,
+        recovery_codes: &[String],
     ) -> Result<()> {
         self.db()
+            .await?
+            .query(
+                r#"
+                UPDATE $recordId SET
+                    totp_secret = $secret,
+                    totp_last_step = $step,
+                    recovery_codes = $recovery_codes.map(|$code| crypto::sha256($code))
+            "#,
+            )
+            .bind(("recordId", id.clone()))
+            .bind(("secret", secret.as_base32().to_string()))
+            .bind(("step", step))
+            .bind(("recovery_codes", recovery_codes.to_vec()))
+            .timed("enable_two_factor")
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    /// Records that the code of `step` was used, unless a code of this step
+    /// or a later one already was.
+    ///
+    /// Returns `false` if the code was already used, e.g. by a concurrent
+    /// request.
+    pub async fn use_totp_step(&self, id: &RecordId, step: u64) -> Result<bool> {
+        // Not `UPDATE ONLY`, which fails when the condition doesn't hold
+        let updated: Vec<RecordId> = self
+            .db()
+            .await?
+            .query(
+                r#"
+                UPDATE $recordId
+                SET totp_last_step = $step
+                WHERE totp_last_step = NONE OR totp_last_step < $step
+                RETURN VALUE id
+            "#,
+            )
+            .bind(("recordId", id.clone()))
+            .bind(("step", step))
+            .timed("use_totp_step")
+            .await?
+            .take(0)?;
+
+        Ok(!updated.is_empty())
+    }
+
+    /// Consumes one of the recovery codes of the user.
+    ///
+    /// Returns `false` if the code is unknown or was already used.
+    pub async fn use_recovery_code(&self, id: &RecordId, code: &str) -> Result<bool> {
+        let updated: Vec<RecordId> = self
+            .db()
+            .await?
+            .query(
+                r#"
+                LET $code_hash = crypto::sha256($code);
+                UPDATE $recordId
+                SET recovery_codes -= $code_hash
+                WHERE recovery_codes CONTAINS $code_hash
+                RETURN VALUE id;
+            "#,
+            )
+            .bind(("recordId", id.clone()))
+            .bind(("code", code.to_string()))
+            .timed("use_recovery_code")
+            .await?
+            .take(1)?;
+
+        Ok(!updated.is_empty())
+    }
+
+    /// Counts a wrong second factor code, locking the second factor for
+    /// `lockout` once `max_failures` were given since the last right one.
+    ///
+    /// Returns whether the second factor is now locked.
+    pub async fn record_failed_code(
+        &self,
+        id: &RecordId,
+        max_failures: u32,
+        lockout: Duration,
+    ) -> Result<bool> {
+        let locked: Vec<RecordId> = self
+            .db()
             .await?
             .query(
                 r#"
                 BEGIN TRANSACTION;
-                LET $subscription_token = (CREATE ONLY subscription_tokens CONTENT { token: $token_val });
-                CREATE subscriptions CONTENT {
-                    email: $email,
-                    name: $name,
-                    token: $subscription_token.id
+                UPDATE $recordId SET failed_codes = (failed_codes ?? 0) + 1;
+                UPDATE $recordId
+                SET locked_until = time::now() + duration::from::millis($lockout)
+                WHERE failed_codes >= $max_failures
+                RETURN VALUE id;
+                COMMIT TRANSACTION;
+            "#,
+            )
+            .bind(("recordId", id.clone()))
+            .bind(("max_failures", max_failures))
+            .bind(("lockout", lockout.as_millis() as u64))
+            .timed("record_failed_code")
+            .await?
+            .take(1)?;
+
+        Ok(!locked.is_empty())
+    }
+
+    /// Forgets the wrong codes given before a right one.
+    pub async fn clear_failed_codes(&self, id: &RecordId) -> Result<()> {
+        self.db()
+            .await?
+            .query("UPDATE $recordId SET failed_codes = NONE, locked_until = NONE")
+            .bind(("recordId", id.clone()))
+            .timed("clear_failed_codes")
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    /// Turns two-factor authentication off, e.g. for a user who lost their
+    /// authenticator app and recovery codes.
+    ///
+    /// Returns `false` if there is no user with this username.
+    pub async fn reset_two_factor(&self, username: &str) -> Result<bool> {
+        let reset: Option<bool> = self
+            .db()
+            .await?
+            .query(
+                r#"
+                BEGIN TRANSACTION;
+                LET $user = (SELECT VALUE id FROM ONLY users WHERE username = $username LIMIT 1);
+                RETURN IF $user {
+                    UPDATE $user SET
+                        totp_secret = NONE,
+                        totp_last_step = NONE,
+                        recovery_codes = NONE,
+                        failed_codes = NONE,
+                        locked_until = NONE;
+                    true
+                } ELSE {
+                    false
                 };
                 COMMIT TRANSACTION;
             "#,
             )
-            .bind(("token_val", token.to_string()))
-            .bind(("email", subscriber.email.as_ref().to_string()))
-            .bind(("name", subscriber.name.as_ref().to_string()))
+            .bind(("username", username.to_string()))
+            .timed("reset_two_factor")
+            .await?
+            .take(0)?;
+
+        reset.ok_or(Error::Custom(
+            "Failed to reset two-factor authentication".into(),
+        ))
+    }
+
+    /// Creates an admin user, unless `username` is already taken.
+    ///
+    /// Returns `false` if the username was already taken.
+    pub async fn create_user(&self, username: &str, password: &NewPassword) -> Result<bool> {
+        let created: Option<bool> = self
+            .db()
+            .await?
+            .query(
+                r#"
+                BEGIN TRANSACTION;
+                RETURN IF (SELECT VALUE id FROM ONLY users WHERE username = $username LIMIT 1) {
+                    false
+                } ELSE {
+                    CREATE users CONTENT {
+                        username: $username,
+                        password: crypto::argon2::generate($password)
+                    };
+                    true
+                };
+                COMMIT TRANSACTION;
+            "#,
+            )
+            .bind(("username", username.to_string()))
+            .bind(("password", password.expose_secret().to_string()))
+            .timed("create_user")
+            .await?
+            .take(0)?;
+
+        created.ok_or(Error::Custom("Failed to create user".into()))
+    }
+
+    pub async fn get_user_id(&self, username: &str) -> Result<Option<RecordId>> {
+        Ok(self
+            .db()
+            .await?
+            .query(r#"SELECT VALUE id FROM ONLY users WHERE username = $username LIMIT 1"#)
+            .bind(("username", username.to_string()))
+            .timed("get_user_id")
+            .await?
+            .take::<Option<RecordId>>(0)?)
+    }
+
+    pub async fn list_users(&self) -> Result<Vec<UserSummary>> {
+        Ok(self
+            .db()
+            .await?
+            .query(
+                r#"
+                SELECT username, <string> created_at AS created_at
+                FROM users
+                ORDER BY username
+            "#,
+            )
+            .timed("list_users")
+            .await?
+            .take::<Vec<UserSummary>>(0)?)
+    }
+
+    /// Deletes the user and the idempotency records, API tokens and sessions
+    /// it owns.
+    ///
+    /// Returns `false` if there is no user with this username.
+    pub async fn delete_user(&self, username: &str) -> Result<bool> {
+        let deleted: Option<RecordId> = self
+            .db()
+            .await?
+            .query(
+                r#"
+                BEGIN TRANSACTION;
+                LET $user = (SELECT VALUE id FROM ONLY users WHERE username = $username LIMIT 1);
+                IF $user {
+                    DELETE idempotency WHERE user = $user;
+                    DELETE api_tokens WHERE user = $user;
+                    DELETE $user;
+                };
+                RETURN $user;
+                COMMIT TRANSACTION;
+            "#,
+            )
+            .bind(("username", username.to_string()))
+            .timed("delete_user")
+            .await?
+            .take(0)?;
+
+        match deleted {
+            Some(user_id) => {
+                self.delete_sessions(&user_id).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Log out every session of `user_id`, including unfinished logins.
+    ///
+    /// The session store keeps the session data encoded, so sessions are
+    /// decoded here to find the ones of the user.
+    async fn delete_sessions(&self, user_id: &RecordId) -> Result<()> {
+        #[derive(Deserialize)]
+        struct StoredSession {
+            id: RecordId,
+            data: Vec<u8>,
+        }
+
+        let db = self.db().await?;
+        let sessions: Vec<StoredSession> = db
+            .query("SELECT id, data FROM type::table($table)")
+            .bind(("table", SESSIONS_TABLE))
+            .timed("list_sessions")
+            .await?
+            .take(0)?;
+
+        let owned = sessions
+            .into_iter()
+            .filter(|session| {
+                rmp_serde::from_slice::<Record>(&session.data)
+                    .is_ok_and(|record| TypedSession::belongs_to(&record, user_id))
+            })
+            .map(|session| session.id)
+            .collect::<Vec<_>>();
+
+        db.query("DELETE $sessions")
+            .bind(("sessions", owned))
+            .timed("delete_sessions")
             .await?
             .check()?;
 
         Ok(())
     }
 
-    pub async fn confirm_subscriber(&self, token: String) -> Result<()> {
+    /// Issues an API token for `user_id`, storing only the hash of `secret`.
+    pub async fn create_api_token(
+        &self,
+        user_id: &RecordId,
+        token: &NewApiToken,
+        secret: &str,
+    ) -> Result<()> {
         self.db()
             .await?
             .query(
                 r#"
-                UPDATE subscriptions
-                SET status = 'CONFIRMED'
-                WHERE token = (
-                    SELECT id
-                    FROM ONLY subscription_tokens
-                    WHERE token = $token_val
-                ).id
-                AND status = 'PENDING';
+                CREATE api_tokens CONTENT {
+                    user: $user,
+                    name: $name,
+                    token_hash: crypto::sha256($secret),
+                    scopes: $scopes,
+                    expires_at: IF $expires_in {
+                        time::now() + duration::from::secs($expires_in)
+                    } ELSE {
+                        NONE
+                    }
+                };
             "#,
             )
-            .bind(("token_val", token))
-            .await?;
+            .bind(("user", user_id.clone()))
+            .bind(("name", token.name.clone()))
+            .bind(("secret", secret.to_string()))
+            .bind(("scopes", token.scopes.clone()))
+            .bind((
+                "expires_in",
+                token.expires_in.map(|expires_in| expires_in.as_secs()),
+            ))
+            .timed("create_api_token")
+            .await?
+            .check()?;
 
         Ok(())
     }
 
-    pub async fn get_confirmed_subscribers(&self) -> Result<Vec<ConfirmedSubscriber>> {
+    pub async fn list_api_tokens(&self) -> Result<Vec<ApiTokenSummary>> {
         Ok(self
             .db()
             .await?
             .query(
                 r#"
-                SELECT * FROM subscriptions
-                WHERE status = 'CONFIRMED';
+                SELECT
+                    id,
+                    name,
+                    user.username AS owner,
+                    scopes,
+                    <string> created_at AS created_at,
+                    IF expires_at { <string> expires_at } AS expires_at,
+                    IF last_used_at { <string> last_used_at } AS last_used_at,
+                    revoked_at != NONE AS revoked,
+                    expires_at != NONE AND expires_at <= time::now() AS expired
+                FROM api_tokens
+                ORDER BY created_at DESC
             "#,
             )
+            .timed("list_api_tokens")
             .await?
-            .take(0)?)
+            .take::<Vec<ApiTokenSummary>>(0)?)
     }
 
-    pub async fn validate_credientials(&self, credentials: Credentials) -> Result<RecordId> {
-        #[derive(Debug, Deserialize)]
-        struct QueryResult {
-            id: RecordId,
-        }
+    /// Returns `false` if there is no such token or it was already revoked.
+    pub async fn revoke_api_token(&self, id: RecordId) -> Result<bool> {
+        // Not `UPDATE ONLY`, which fails when the condition doesn't hold
+        let revoked: Vec<RecordId> = self
+            .db()
+            .await?
+            .query(
+                r#"
+                UPDATE $recordId
+                SET revoked_at = time::now()
+                WHERE revoked_at = NONE
+                RETURN VALUE id;
+            "#,
+            )
+            .bind(("recordId", id))
+            .timed("revoke_api_token")
+            .await?
+            .take(0)?;
+
+        Ok(!revoked.is_empty())
+    }
+
+    /// Looks up the live token matching `secret`, recording that it was used.
+    ///
+    /// Returns `None` for unknown, expired and revoked tokens.
+    pub async fn authenticate_api_token(&self, secret: &str) -> Result<Option<ApiClient>> {
+        Ok(self
+            .db()
+            .await?
+            .query(
+                r#"
+                BEGIN TRANSACTION;
+                LET $api_token = (
+                    SELECT VALUE id
+                    FROM ONLY api_tokens
+                    WHERE token_hash = crypto::sha256($secret)
+                        AND revoked_at = NONE
+                        AND (expires_at = NONE OR expires_at > time::now())
+                    LIMIT 1
+                );
+                IF $api_token {
+                    UPDATE $api_token SET last_used_at = time::now();
+                };
+                RETURN IF $api_token {
+                    (SELECT user, user.username AS username, scopes FROM ONLY $api_token)
+                };
+                COMMIT TRANSACTION;
+            "#,
+            )
+            .bind(("secret", secret.to_string()))
+            .timed("authenticate_api_token")
+            .await?
+            .take::<Option<ApiClient>>(0)?)
+    }
+
+    /// Reserve `key` for `user_id` on `endpoint`, taking it over if its
+    /// response was saved more than `key_ttl` ago or if it has been in
+    /// progress for more than `claim_ttl`.
+    ///
+    /// Returns `None` when the key was free and is now reserved for the caller,
+    /// or the record already holding the key otherwise.
+    pub async fn try_insert_idempotency_key(
+        &self,
+        user_id: &RecordId,
+        endpoint: IdempotencyEndpoint,
+        key: &IdempotencyKey,
+        config: &IdempotencyConfig,
+    ) -> Result<Option<IdempotencyRecord>> {
         let result = self
             .db()
             .await?
             .query(
                 r#"
-                SELECT id
-                FROM ONLY users
-                WHERE username = $username AND crypto::argon2::compare(password, $password);
-                "#,
+                BEGIN TRANSACTION;
+                DELETE idempotency
+                WHERE user = $user AND endpoint = $endpoint AND idempotency_key = $key
+                    AND created_at < time::now() - duration::from::millis(
+                        IF response = NONE { $claim_ttl } ELSE { $key_ttl }
+                    );
+                LET $existing = (
+                    SELECT response
+                    FROM ONLY idempotency
+                    WHERE user = $user AND endpoint = $endpoint AND idempotency_key = $key
+                    LIMIT 1
+                );
+                IF $existing = NONE {
+                    CREATE idempotency CONTENT {
+                        user: $user,
+                        endpoint: $endpoint,
+                        idempotency_key: $key,
+                    };
+                };
+                RETURN $existing;
+                COMMIT TRANSACTION;
+            "#,
             )
-            .bind(("username", credentials.username))
-            .bind(("password", credentials.password.expose_secret().to_string()))
+            .bind(("user", user_id.clone()))
+            .bind(("endpoint", endpoint))
+            .bind(("key", key.as_ref().to_string()))
+            .bind(("key_ttl", config.key_ttl.as_millis() as u64))
+            .bind(("claim_ttl", config.claim_ttl.as_millis() as u64))
+            .timed("try_insert_idempotency_key")
+            .await
+            .and_then(|mut response| response.take::<Option<IdempotencyRecord>>(0));
+
+        match result {
+            Ok(record) => Ok(record),
+            // A concurrent request reserved the same key first
+            Err(err) => match self.get_idempotency_record(user_id, endpoint, key).await? {
+                Some(record) => Ok(Some(record)),
+                None => Err(err.into()),
+            },
+        }
+    }
+
+    /// Forget the idempotency keys whose response was saved more than
+    /// `key_ttl` ago, returning how many were deleted.
+    ///
+    /// Keys still in progress are kept, their request may yet save a response.
+    pub async fn delete_expired_idempotency_keys(&self, key_ttl: Duration) -> Result<usize> {
+        let deleted: Vec<RecordId> = self
+            .db()
             .await?
-            .take::<Option<QueryResult>>(0)?
-            .ok_or(Error::Custom("User not found!".into()))?;
-        Ok(result.id)
+            .query(
+                r#"
+                DELETE idempotency
+                WHERE response != NONE
+                    AND created_at < time::now() - duration::from::millis($key_ttl)
+                RETURN BEFORE;
+            "#,
+            )
+            .bind(("key_ttl", key_ttl.as_millis() as u64))
+            .timed("delete_expired_idempotency_keys")
+            .await?
+            .take((0, "id"))?;
+
+        Ok(deleted.len())
     }
 
-    pub async fn get_username(&self, id: RecordId) -> Result<String> {
+    pub async fn get_idempotency_record(
+        &self,
+        user_id: &RecordId,
+        endpoint: IdempotencyEndpoint,
+        key: &IdempotencyKey,
+    ) -> Result<Option<IdempotencyRecord>> {
+        Ok(self
+            .db()
+            .await?
+            .query(
+                r#"
+                SELECT response
+                FROM ONLY idempotency
+                WHERE user = $user AND endpoint = $endpoint AND idempotency_key = $key
+                LIMIT 1;
+            "#,
+            )
+            .bind(("user", user_id.clone()))
+            .bind(("endpoint", endpoint))
+            .bind(("key", key.as_ref().to_string()))
+            .timed("get_idempotency_record")
+            .await?
+            .take(0)?)
+    }
+
+    pub async fn save_idempotent_response(
+        &self,
+        user_id: &RecordId,
+        endpoint: IdempotencyEndpoint,
+        key: &IdempotencyKey,
+        response: SavedResponse,
+    ) -> Result<()> {
         self.db()
             .await?
-            .query(r#"SELECT VALUE username FROM ONLY $recordId"#)
-            .bind(("recordId", id))
+            .query(
+                r#"
+                UPDATE idempotency
+                SET response = $response
+                WHERE user = $user AND endpoint = $endpoint AND idempotency_key = $key;
+            "#,
+            )
+            .bind(("user", user_id.clone()))
+            .bind(("endpoint", endpoint))
+            .bind(("key", key.as_ref().to_string()))
+            .bind(("response", response))
+            .timed("save_idempotent_response")
             .await?
-            .take::<Option<String>>(0)?
-            .ok_or(Error::Custom("User with this id don't exists".into()))
+            .check()?;
+
+        Ok(())
+    }
+
+    pub async fn delete_idempotency_key(
+        &self,
+        user_id: &RecordId,
+        endpoint: IdempotencyEndpoint,
+        key: &IdempotencyKey,
+    ) -> Result<()> {
+        self.db()
+            .await?
+            .query(
+                r#"
+                DELETE idempotency
+                WHERE user = $user AND endpoint = $endpoint AND idempotency_key = $key;
+            "#,
+            )
+            .bind(("user", user_id.clone()))
+            .bind(("endpoint", endpoint))
+            .bind(("key", key.as_ref().to_string()))
+            .timed("delete_idempotency_key")
+            .await?
+            .check()?;
+
+        Ok(())
     }
 
     async fn connect(&self) -> Result<Surreal<Any>> {
@@ -152,13 +1630,20 @@ impl ModelManager {
         })
         .await?;
 
-        // Apply Migrations
-        MigrationRunner::new(&db)
-            .load_files(&include_dir!("$CARGO_MANIFEST_DIR/surrealdb"))
-            .up()
-            .await
-            .map_err(|e| Error::Migrations(e.to_string()))?;
-
         Ok(db)
     }
 }
+
+/// Records how long a query takes in the `db_query_duration_seconds`
+/// histogram, labelled with the `operation` it belongs to.
+trait Timed: IntoFuture + Sized {
+    async fn timed(self, operation: &'static str) -> Self::Output {
+        let started_at = Instant::now();
+        let output = self.await;
+        metrics::histogram!("db_query_duration_seconds", "operation" => operation)
+            .record(started_at.elapsed().as_secs_f64());
+        output
+    }
+}
+
+impl<Q: IntoFuture> Timed for Q {}
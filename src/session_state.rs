@@ -5,14 +5,20 @@ use axum::{
 };
 use reqwest::StatusCode;
 use surrealdb::RecordId;
-use tower_sessions::Session;
+use tower_sessions::{Session, session::Record};
 
-use crate::Result;
+use crate::{Result, domain::TotpSecret};
+
+/// The table of the session store.
+pub const SESSIONS_TABLE: &str = "sessions";
 
 pub struct TypedSession(Session);
 
 impl TypedSession {
     const USER_ID_KEY: &str = "user_id";
+    const USERNAME_KEY: &str = "username";
+    const PENDING_USER_ID_KEY: &str = "pending_user_id";
+    const TOTP_ENROLLMENT_KEY: &str = "totp_enrollment";
 
     pub async fn renew(&self) -> Result<()> {
         Ok(self.0.cycle_id().await?)
@@ -25,6 +31,68 @@ impl TypedSession {
     pub async fn get_user_id(&self) -> Result<Option<RecordId>> {
         Ok(self.0.get(Self::USER_ID_KEY).await?)
     }
+
+    /// Keep the username of the logged in user, sparing a lookup per request.
+    pub async fn insert_username(&self, username: String) -> Result<()> {
+        Ok(self.0.insert(Self::USERNAME_KEY, username).await?)
+    }
+
+    pub async fn get_username(&self) -> Result<Option<String>> {
+        Ok(self.0.get(Self::USERNAME_KEY).await?)
+    }
+
+    /// Remember the user who gave the right password but still has to give
+    /// their second factor.
+    pub async fn insert_pending_user_id(&self, user_id: RecordId) -> Result<()> {
+        Ok(self.0.insert(Self::PENDING_USER_ID_KEY, user_id).await?)
+    }
+
+    pub async fn get_pending_user_id(&self) -> Result<Option<RecordId>> {
+        Ok(self.0.get(Self::PENDING_USER_ID_KEY).await?)
+    }
+
+    /// Forget the pending login, once it completed or was abandoned.
+    pub async fn remove_pending_user_id(&self) -> Result<()> {
+        self.0.remove::<RecordId>(Self::PENDING_USER_ID_KEY).await?;
+        Ok(())
+    }
+
+    /// The secret shown to the user while they enroll their authenticator
+    /// app, kept until they confirm it with a first code.
+    pub async fn insert_totp_enrollment(&self, secret: &TotpSecret) -> Result<()> {
+        Ok(self
+            .0
+            .insert(Self::TOTP_ENROLLMENT_KEY, secret.as_base32())
+            .await?)
+    }
+
+    pub async fn get_totp_enrollment(&self) -> Result<Option<TotpSecret>> {
+        Ok(self
+            .0
+            .get::<String>(Self::TOTP_ENROLLMENT_KEY)
+            .await?
+            .map(TotpSecret::from_base32))
+    }
+
+    pub async fn remove_totp_enrollment(&self) -> Result<()> {
+        self.0.remove::<String>(Self::TOTP_ENROLLMENT_KEY).await?;
+        Ok(())
+    }
+
+    pub async fn log_out(&self) -> Result<()> {
+        Ok(self.0.flush().await?)
+    }
+
+    /// Whether the stored session `record` is logged in, or logging in, as
+    /// `user_id`.
+    pub fn belongs_to(record: &Record, user_id: &RecordId) -> bool {
+        [Self::USER_ID_KEY, Self::PENDING_USER_ID_KEY]
+            .iter()
+            .filter_map(|key| record.data.get(*key))
+            .any(|value| {
+                serde_json::from_value::<RecordId>(value.clone()).is_ok_and(|id| &id == user_id)
+            })
+    }
 }
 
 impl<S> FromRequestParts<S> for TypedSession
@@ -0,0 +1,73 @@
+use crate::{Error, Result, state::AppState};
+use axum::Router;
+use tokio::net::TcpListener;
+
+/// Resolves once the process receives SIGINT or SIGTERM.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for SIGINT");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+}
+
+/// Serves `router` until `signal` resolves, then stops accepting connections
+/// and gives in-flight requests and background tasks up to the configured
+/// shutdown timeout to finish.
+pub async fn serve_until_stopped(
+    listener: TcpListener,
+    router: Router,
+    state: AppState,
+    signal: impl Future<Output = ()>,
+) -> Result<()> {
+    let mut server = tokio::spawn(
+        axum::serve(listener, router)
+            .with_graceful_shutdown(state.shutdown.clone().cancelled_owned())
+            .into_future(),
+    );
+
+    tokio::select! {
+        () = signal => {}
+        result = &mut server => {
+            state.shutdown.cancel();
+            return Ok(result.map_err(|err| Error::Custom(err.to_string()))??);
+        }
+    }
+
+    let deadline = state.config.shutdown.timeout;
+    tracing::info!("Shutdown requested, draining in-flight work for up to {deadline:?}");
+    state.shutdown.cancel();
+    state.tasks.close();
+
+    let drained = tokio::time::timeout(deadline, async {
+        match server.await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => tracing::error!("Server stopped with an error: {err:?}"),
+            Err(err) => tracing::error!("Server task failed: {err:?}"),
+        }
+        state.tasks.wait().await;
+    })
+    .await;
+
+    match drained {
+        Ok(()) => tracing::info!("Server gracefully shutdown"),
+        Err(_) => tracing::warn!("Shutdown timeout elapsed, abandoning in-flight work"),
+    }
+
+    Ok(())
+}
@@ -1,15 +1,34 @@
 use crate::{
     Result,
+    authentication::{RequiredScope, reject_anonymous_users, reject_unauthorized_clients},
     config::Config,
-    handlers::{admin_dashboard, confirm, health, home, login, publish_newsletter, subscribe},
+    domain::ApiScope,
+    errors::scope_request_id,
+    handlers::{
+        ApiDoc, IMPORT_BODY_LIMIT, admin_api_tokens, admin_change_password, admin_create_api_token,
+        admin_dashboard, admin_dead_letters, admin_disable_two_factor, admin_enable_two_factor,
+        admin_logout, admin_newsletter_form, admin_password_form, admin_publish_newsletter,
+        admin_revoke_api_token, admin_subscriber, admin_subscribers, admin_two_factor, api_confirm,
+        api_publish_newsletter, api_subscribe, api_subscriber, api_unsubscribe, confirm,
+        export_subscribers, health, health_live, health_ready, home, import_subscribers,
+        import_subscribers_form, login, metrics, openapi_json, publish_newsletter,
+        replay_dead_letter, subscribe, unsubscribe, unsubscribe_form, unsubscribe_one_click,
+    },
+    idempotency::run_expiry_until_stopped,
+    issue_delivery_worker::run_worker_until_stopped,
+    session_state::SESSIONS_TABLE,
     state::AppState,
+    telemetry::{set_remote_parent, track_http_metrics},
 };
 use axum::{
     Router,
+    extract::DefaultBodyLimit,
     http::{HeaderName, Request},
+    middleware::{from_fn, from_fn_with_state},
     routing::{get, post},
 };
 use axum_messages::MessagesManagerLayer;
+use tokio::net::TcpListener;
 use tower::ServiceBuilder;
 use tower_cookies::CookieManagerLayer;
 use tower_http::{
@@ -18,38 +37,171 @@ use tower_http::{
 };
 use tower_sessions::SessionManagerLayer;
 use tower_sessions_surrealdb_store::SurrealSessionStore;
+use utoipa::OpenApi;
+use utoipa_scalar::{Scalar, Servable};
 
-const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+pub(crate) const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
 
 pub async fn init(config: Config) -> Result<(Router, AppState)> {
     let state = AppState::new(config).await?;
 
+    // Connect and check the schema now rather than on the first request
+    state.mm.prepare_schema().await?;
+
+    // Deliver queued newsletter issues in the background
+    state.tasks.spawn(run_worker_until_stopped(state.clone()));
+    // Forget idempotency keys once retries are no longer expected
+    state.tasks.spawn(run_expiry_until_stopped(state.clone()));
+
     let middleware = ServiceBuilder::new()
         .layer(SetRequestIdLayer::new(REQUEST_ID_HEADER, MakeRequestUuid))
+        .layer(from_fn(scope_request_id))
         .layer(
             TraceLayer::new_for_http().make_span_with(|request: &Request<_>| {
                 // Log the request id as generated.
-                let request_id = request.headers().get(REQUEST_ID_HEADER);
-                tracing::info_span!("http_request", method = ?request.method(), request_id = ?request_id, uri = ?request.uri())
+                let request_id = request
+                    .headers()
+                    .get(REQUEST_ID_HEADER)
+                    .and_then(|request_id| request_id.to_str().ok());
+                let span = tracing::info_span!("http_request", method = ?request.method(), request_id, uri = ?request.uri());
+                set_remote_parent(&span, request.headers());
+                span
             }),
         )
         // send headers from request to response headers
         .layer(PropagateRequestIdLayer::new(REQUEST_ID_HEADER))
         .layer(CookieManagerLayer::new())
-        .layer(SessionManagerLayer::new(SurrealSessionStore::new(state.mm.db().await?.clone(), "sessions".into())))
+        .layer(SessionManagerLayer::new(SurrealSessionStore::new(state.mm.db().await?.clone(), SESSIONS_TABLE.into())))
         .layer(MessagesManagerLayer);
 
     let router = Router::new()
         .route("/", get(home))
         .route("/health", get(health))
+        .route("/health/live", get(health_live))
+        .route("/health/ready", get(health_ready))
         .route("/subscriptions", post(subscribe))
         .route("/subscriptions/confirm", get(confirm))
-        .route("/newsletter", post(publish_newsletter))
+        .route("/subscriptions/unsubscribe", get(unsubscribe_form))
+        .route("/subscriptions/unsubscribe", post(unsubscribe))
+        .route(
+            "/subscriptions/unsubscribe/one-click",
+            post(unsubscribe_one_click),
+        )
+        .route(
+            "/newsletter",
+            post(publish_newsletter).route_layer(from_fn_with_state(
+                RequiredScope::new(state.mm.clone(), ApiScope::NewsletterPublish),
+                reject_unauthorized_clients,
+            )),
+        )
         .route("/login", get(login::get::login))
         .route("/login", post(login::post::login))
-        .route("/admin/dashboard", get(admin_dashboard))
+        .route(
+            "/login/two-factor",
+            get(login::two_factor::form).post(login::two_factor::verify),
+        )
+        .nest("/admin", admin_router(&state))
+        .nest("/api/v1", api_router(&state))
+        .route("/api/openapi.json", get(openapi_json))
+        .merge(Scalar::with_url("/api/docs", ApiDoc::openapi()));
+
+    // Without a separate metrics server, only let API clients scrape them
+    let router = match state.config.metrics.port {
+        Some(_) => router,
+        None => router.route(
+            "/metrics",
+            get(metrics).route_layer(from_fn_with_state(
+                RequiredScope::new(state.mm.clone(), ApiScope::MetricsRead),
+                reject_unauthorized_clients,
+            )),
+        ),
+    };
+
+    let router = router
+        .layer(from_fn(track_http_metrics))
         .layer(middleware)
         .with_state(state.clone());
 
     Ok((router, state))
 }
+
+/// The router of `/metrics`, served apart from the application when
+/// [`MetricsConfig::port`](crate::config::MetricsConfig::port) is set.
+pub fn metrics_router(state: &AppState) -> Router {
+    Router::new()
+        .route("/metrics", get(metrics))
+        .with_state(state.clone())
+}
+
+/// Serve `/metrics` on `listener`, bound to
+/// [`MetricsConfig::port`](crate::config::MetricsConfig::port), until the app
+/// shuts down.
+pub fn spawn_metrics_server(listener: TcpListener, state: &AppState) {
+    let server = axum::serve(listener, metrics_router(state))
+        .with_graceful_shutdown(state.shutdown.clone().cancelled_owned());
+
+    state.tasks.spawn(async move {
+        if let Err(err) = server.await {
+            tracing::error!("Metrics server stopped with an error: {err:?}");
+        }
+    });
+}
+
+/// JSON endpoints for the mobile app and partner integrations.
+fn api_router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route("/subscriptions", post(api_subscribe))
+        .route("/subscriptions/confirm", post(api_confirm))
+        .route("/subscriptions/unsubscribe", post(api_unsubscribe))
+        .route(
+            "/subscribers/{id}",
+            get(api_subscriber).route_layer(from_fn_with_state(
+                RequiredScope::new(state.mm.clone(), ApiScope::SubscribersRead),
+                reject_unauthorized_clients,
+            )),
+        )
+        .route(
+            "/newsletters",
+            post(api_publish_newsletter).route_layer(from_fn_with_state(
+                RequiredScope::new(state.mm.clone(), ApiScope::NewsletterPublish),
+                reject_unauthorized_clients,
+            )),
+        )
+}
+
+/// Routes only available to logged in users.
+fn admin_router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route("/dashboard", get(admin_dashboard))
+        .route(
+            "/newsletters",
+            get(admin_newsletter_form).post(admin_publish_newsletter),
+        )
+        .route(
+            "/password",
+            get(admin_password_form).post(admin_change_password),
+        )
+        .route(
+            "/two-factor",
+            get(admin_two_factor).post(admin_enable_two_factor),
+        )
+        .route("/two-factor/disable", post(admin_disable_two_factor))
+        .route("/logout", post(admin_logout))
+        .route("/subscribers", get(admin_subscribers))
+        .route("/subscribers/export.csv", get(export_subscribers))
+        .route(
+            "/subscribers/import",
+            get(import_subscribers_form)
+                .post(import_subscribers)
+                .layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
+        .route("/subscribers/{id}", get(admin_subscriber))
+        .route(
+            "/api-tokens",
+            get(admin_api_tokens).post(admin_create_api_token),
+        )
+        .route("/api-tokens/{id}/revoke", post(admin_revoke_api_token))
+        .route("/deliveries/failed", get(admin_dead_letters))
+        .route("/deliveries/failed/{id}/replay", post(replay_dead_letter))
+        .route_layer(from_fn_with_state(state.mm.clone(), reject_anonymous_users))
+}
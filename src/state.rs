@@ -1,24 +1,40 @@
 use crate::{
     Config, Result,
-    email_client::EmailClient,
+    email_client::{EmailSender, build_email_client},
+    handlers::CachedCheck,
     model::{self, ModelManager},
+    telemetry,
 };
 use axum::extract::FromRef;
+use metrics_exporter_prometheus::PrometheusHandle;
 use std::sync::Arc;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 
 #[derive(Debug, Clone)]
 pub struct AppState {
     pub config: Arc<Config>,
     pub mm: Arc<ModelManager>,
-    pub email_client: Arc<EmailClient>,
+    pub email_client: Arc<dyn EmailSender>,
+    /// The last readiness check of the email provider.
+    pub email_check: Arc<CachedCheck>,
+    /// Cancelled once the process is asked to shut down.
+    pub shutdown: CancellationToken,
+    /// Background tasks to wait for before exiting.
+    pub tasks: TaskTracker,
+    /// Renders the metrics recorded by the whole process.
+    pub metrics: PrometheusHandle,
 }
 
 impl AppState {
     pub async fn new(config: Config) -> Result<Self> {
         Ok(Self {
             mm: Arc::new(model::ModelManager::new(config.database.clone())),
-            email_client: Arc::new(EmailClient::new(config.email_client.clone())?),
+            email_client: build_email_client(config.email_client.clone())?,
+            email_check: Arc::default(),
             config: Arc::new(config),
+            shutdown: CancellationToken::new(),
+            tasks: TaskTracker::new(),
+            metrics: telemetry::prometheus_handle(),
         })
     }
 }
@@ -29,7 +45,7 @@ impl FromRef<AppState> for Arc<model::ModelManager> {
     }
 }
 
-impl FromRef<AppState> for Arc<EmailClient> {
+impl FromRef<AppState> for Arc<dyn EmailSender> {
     fn from_ref(input: &AppState) -> Self {
         input.email_client.clone()
     }
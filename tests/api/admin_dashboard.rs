@@ -14,3 +14,45 @@ async fn you_must_be_logged_to_access_admin_dashboard() {
     assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
     assert_eq!(response.header(header::LOCATION), "/login");
 }
+
+#[tokio::test]
+async fn anonymous_json_requests_to_admin_routes_are_rejected_with_401() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+
+    // Act
+    let response = app
+        .server
+        .get("/admin/deliveries/failed")
+        .add_header(header::ACCEPT, "application/json")
+        .await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.header("Content-Type"), "application/problem+json");
+    let problem = response.json::<serde_json::Value>();
+    assert_eq!(problem["status"], 401);
+    assert_eq!(problem["code"], "UNAUTHORIZED");
+    assert_eq!(problem["detail"], "Authentication required");
+}
+
+#[tokio::test]
+async fn sessions_of_deleted_users_are_rejected() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    app.login().await;
+    let deleted = app
+        .state
+        .mm
+        .delete_user(&app.test_user.username)
+        .await
+        .expect("Expected the user to be deleted");
+    assert!(deleted);
+
+    // Act
+    let response = app.server.get("/admin/dashboard").await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+    assert_eq!(response.header(header::LOCATION), "/login");
+}
@@ -0,0 +1,165 @@
+use crate::{helpers::TestApp, newsletter::create_confirmed_subscriber};
+use reqwest::{Method, StatusCode, header};
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{any, method},
+};
+
+fn newsletter_form() -> [(&'static str, String); 4] {
+    [
+        ("title", "Newsletter title".into()),
+        ("html_content", "<p>Newsletter body as html</p>".into()),
+        ("text_content", "Newsletter body as plain text".into()),
+        ("idempotency_key", idempotency_key()),
+    ]
+}
+
+fn idempotency_key() -> String {
+    use rand::distr::{Alphanumeric, SampleString};
+    Alphanumeric.sample_string(&mut rand::rng(), 25)
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_send_newsletters() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+
+    // Act
+    let form = app.server.get("/admin/newsletters").await;
+    let submission = app
+        .server
+        .post("/admin/newsletters")
+        .form(&newsletter_form())
+        .await;
+
+    // Assert
+    for response in [form, submission] {
+        assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+        assert_eq!(response.header(header::LOCATION), "/login");
+    }
+}
+
+#[tokio::test]
+async fn newsletter_form_is_shown_to_logged_in_users() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    app.login().await;
+
+    // Act
+    let response = app.server.get("/admin/newsletters").await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert!(
+        response
+            .text()
+            .contains(r#"<form action="/admin/newsletters" method="post">"#)
+    );
+}
+
+#[tokio::test]
+async fn sending_a_newsletter_queues_it_for_confirmed_subscribers() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .server
+        .post("/admin/newsletters")
+        .form(&newsletter_form())
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+    assert_eq!(response.header(header::LOCATION), "/admin/dashboard");
+
+    let dashboard = app.server.get("/admin/dashboard").await;
+    assert!(
+        dashboard
+            .text()
+            .contains("The newsletter issue has been queued for 1 subscribers")
+    );
+}
+
+#[tokio::test]
+async fn incomplete_newsletters_are_rejected() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .server
+        .post("/admin/newsletters")
+        .form(&[
+            ("title", "Newsletter title"),
+            ("html_content", ""),
+            ("text_content", "Newsletter body as plain text"),
+            ("idempotency_key", "a-key"),
+        ])
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+    assert_eq!(response.header(header::LOCATION), "/admin/newsletters");
+
+    let form = app.server.get("/admin/newsletters").await;
+    assert!(
+        form.text()
+            .contains("The title, HTML and text content are all required")
+    );
+}
+
+#[tokio::test]
+async fn resubmitting_the_form_publishes_the_issue_once() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let page = app.server.get("/admin/newsletters").await.text();
+    let idempotency_key = page
+        .split(r#"name="idempotency_key" value=""#)
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .expect("Expected the form to embed an idempotency key")
+        .to_string();
+    let mut form = newsletter_form();
+    form[3].1 = idempotency_key;
+
+    // Act
+    let first = app.server.post("/admin/newsletters").form(&form).await;
+    let second = app.server.post("/admin/newsletters").form(&form).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    for response in [first, second] {
+        assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+        assert_eq!(response.header(header::LOCATION), "/admin/dashboard");
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{helpers::TestApp, newsletter::create_confirmed_subscriber};
+use reqwest::{Method, StatusCode, header};
+use std::collections::HashSet;
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{any, method},
+};
+
+/// Insert `count` subscribers named `{prefix}{i}` with the given status.
+async fn insert_subscribers(app: &TestApp, prefix: &str, status: &str, count: usize) {
+    app.state
+        .mm
+        .db()
+        .await
+        .expect("Expected Database to be connected")
+        .query(
+            r#"
+            FOR $i IN 0..$count {
+                LET $name = string::concat($prefix, <string> $i);
+                CREATE subscriptions CONTENT {
+                    email: string::concat($name, '@example.com'),
+                    name: $name,
+                    status: $status,
+                    token: (CREATE ONLY subscription_tokens CONTENT { token: rand::string(25) }).id,
+                    unsubscribe_token: rand::string(25)
+                };
+            };
+        "#,
+        )
+        .bind(("prefix", prefix.to_string()))
+        .bind(("status", status.to_string()))
+        .bind(("count", count))
+        .await
+        .expect("query should be successful")
+        .check()
+        .expect("subscribers should be inserted");
+}
+
+/// The emails listed in a subscribers page.
+fn listed_emails(page: &str) -> Vec<String> {
+    page.split(r#"<td><a href="/admin/subscribers/"#)
+        .skip(1)
+        .map(|row| {
+            let start = row.find('>').unwrap() + 1;
+            let end = row.find("</a>").unwrap();
+            row[start..end].to_string()
+        })
+        .collect()
+}
+
+/// The link to the next page, without the HTML escaping.
+fn next_page_link(page: &str) -> Option<String> {
+    let start = page.find(r#"<a href="/admin/subscribers?"#)? + r#"<a href=""#.len();
+    let end = start + page[start..].find('"')?;
+    Some(page[start..end].replace("&amp;", "&"))
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_subscribers() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+
+    // Act
+    let response = app.server.get("/admin/subscribers").await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+    assert_eq!(response.header(header::LOCATION), "/login");
+}
+
+#[tokio::test]
+async fn subscribers_are_paginated_with_a_cursor() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    insert_subscribers(&app, "reader", "CONFIRMED", 25).await;
+    app.login().await;
+
+    // Act
+    let first_page = app.server.get("/admin/subscribers").await.text();
+    let next_link = next_page_link(&first_page).expect("Expected a next page link");
+    let second_page = app.server.get(&next_link).await.text();
+
+    // Assert
+    let first_emails = listed_emails(&first_page);
+    let second_emails = listed_emails(&second_page);
+    assert_eq!(first_emails.len(), 20);
+    assert_eq!(second_emails.len(), 5);
+    assert!(next_page_link(&second_page).is_none());
+
+    let all_emails = first_emails
+        .into_iter()
+        .chain(second_emails)
+        .collect::<HashSet<_>>();
+    assert_eq!(all_emails.len(), 25);
+}
+
+#[tokio::test]
+async fn subscribers_can_be_sorted_by_creation_date() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    insert_subscribers(&app, "early", "CONFIRMED", 1).await;
+    insert_subscribers(&app, "late", "CONFIRMED", 1).await;
+    app.login().await;
+
+    // Act
+    let newest_first = app.server.get("/admin/subscribers").await.text();
+    let oldest_first = app
+        .server
+        .get("/admin/subscribers?sort=oldest")
+        .await
+        .text();
+
+    // Assert
+    assert_eq!(
+        listed_emails(&newest_first),
+        ["late0@example.com", "early0@example.com"]
+    );
+    assert_eq!(
+        listed_emails(&oldest_first),
+        ["early0@example.com", "late0@example.com"]
+    );
+}
+
+#[tokio::test]
+async fn subscribers_can_be_filtered_by_status_and_searched_by_prefix() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    insert_subscribers(&app, "alice", "CONFIRMED", 2).await;
+    insert_subscribers(&app, "bob", "PENDING", 3).await;
+    app.login().await;
+
+    // Act
+    let pending = app
+        .server
+        .get("/admin/subscribers?status=PENDING")
+        .await
+        .text();
+    let search = app.server.get("/admin/subscribers?q=ALI").await.text();
+    let both = app
+        .server
+        .get("/admin/subscribers?status=PENDING&q=alice")
+        .await
+        .text();
+
+    // Assert
+    let pending = listed_emails(&pending);
+    assert_eq!(pending.len(), 3);
+    assert!(pending.iter().all(|email| email.starts_with("bob")));
+
+    let search = listed_emails(&search);
+    assert_eq!(search.len(), 2);
+    assert!(search.iter().all(|email| email.starts_with("alice")));
+
+    assert!(listed_emails(&both).is_empty());
+}
+
+#[tokio::test]
+async fn invalid_filters_are_rejected_with_400() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    app.login().await;
+
+    // Act
+    let status = app.server.get("/admin/subscribers?status=UNKNOWN").await;
+    let cursor = app.server.get("/admin/subscribers?cursor=invalid").await;
+
+    // Assert
+    assert_eq!(status.status_code(), StatusCode::BAD_REQUEST);
+    assert_eq!(cursor.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn subscriber_details_show_token_and_delivery_history() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.state
+        .mm
+        .enqueue_newsletter_issue("Newsletter title", "plain text", "<p>html</p>")
+        .await
+        .expect("Expected the issue to be queued");
+    app.dispatch_all_pending_emails().await;
+    app.login().await;
+
+    let list = app.server.get("/admin/subscribers").await.text();
+    let start = list.find(r#"href="/admin/subscribers/"#).unwrap() + r#"href=""#.len();
+    let end = start + list[start..].find('"').unwrap();
+    let detail_link = list[start..end].to_string();
+
+    // Act
+    let response = app.server.get(&detail_link).await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let page = response.text();
+    assert!(page.contains("ursula_le_guin@gmail.com"));
+    assert!(page.contains("Status: CONFIRMED"));
+    assert!(page.contains("<td>Current</td>"));
+    assert!(page.contains("<td>Newsletter title</td><td>DELIVERED</td>"));
+}
+
+#[tokio::test]
+async fn unknown_subscribers_are_rejected_with_404() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    app.login().await;
+
+    // Act
+    let response = app.server.get("/admin/subscribers/unknown").await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+}
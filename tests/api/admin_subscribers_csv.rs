@@ -0,0 +1,302 @@
+use crate::helpers::TestApp;
+use axum_test::multipart::{MultipartForm, Part};
+use reqwest::{Method, StatusCode, header};
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{any, method},
+};
+
+/// An upload of `csv` with the given import mode.
+fn import_form(csv: &str, mode: &str, attestation: bool) -> MultipartForm {
+    let form = MultipartForm::new()
+        .add_part(
+            "file",
+            Part::bytes(csv.as_bytes().to_vec())
+                .file_name("subscribers.csv")
+                .mime_type("text/csv"),
+        )
+        .add_text("mode", mode.to_string());
+
+    if attestation {
+        form.add_text("attestation", "on")
+    } else {
+        form
+    }
+}
+
+async fn subscriber_status(app: &TestApp, email: &str) -> Option<String> {
+    app.state
+        .mm
+        .db()
+        .await
+        .expect("Expected Database to be connected")
+        .query("SELECT VALUE status FROM ONLY subscriptions WHERE email = $email LIMIT 1")
+        .bind(("email", email.to_string()))
+        .await
+        .expect("query should be successful")
+        .take::<Option<String>>(0)
+        .expect("query result should be valid")
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_export_or_import_subscribers() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+
+    // Act
+    let export = app.server.get("/admin/subscribers/export.csv").await;
+    let import = app
+        .server
+        .post("/admin/subscribers/import")
+        .multipart(import_form(
+            "email,name\nursula@example.com,Ursula\n",
+            "confirmed",
+            true,
+        ))
+        .await;
+
+    // Assert
+    for response in [export, import] {
+        assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+        assert_eq!(response.header(header::LOCATION), "/login");
+    }
+    assert_eq!(subscriber_status(&app, "ursula@example.com").await, None);
+}
+
+#[tokio::test]
+async fn exported_csv_lists_every_subscriber_with_status() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    app.login().await;
+    app.server
+        .post("/admin/subscribers/import")
+        .multipart(import_form(
+            "email,name\nursula@example.com,Ursula\nterry@example.com,\"Pratchett, Terry\"\n",
+            "confirmed",
+            true,
+        ))
+        .await;
+
+    // Act
+    let response = app.server.get("/admin/subscribers/export.csv").await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert!(
+        response
+            .header(header::CONTENT_TYPE)
+            .to_str()
+            .unwrap()
+            .starts_with("text/csv")
+    );
+
+    let mut reader = csv::Reader::from_reader(response.as_bytes().as_ref());
+    assert_eq!(
+        reader.headers().unwrap(),
+        vec!["email", "name", "status", "created_at"]
+    );
+    let rows = reader
+        .records()
+        .map(|record| record.expect("Expected a valid CSV record"))
+        .collect::<Vec<_>>();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(&rows[0][0], "ursula@example.com");
+    assert_eq!(&rows[1][1], "Pratchett, Terry");
+    assert!(rows.iter().all(|row| &row[2] == "CONFIRMED"));
+    assert!(rows.iter().all(|row| !row[3].is_empty()));
+}
+
+#[tokio::test]
+async fn exported_cells_are_not_evaluated_as_formulas() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    app.login().await;
+    app.server
+        .post("/admin/subscribers/import")
+        .multipart(import_form(
+            "email,name\n-ursula@example.com,=1+2\nterry@example.com,@SUM\n",
+            "confirmed",
+            true,
+        ))
+        .await;
+
+    // Act
+    let response = app.server.get("/admin/subscribers/export.csv").await;
+
+    // Assert
+    let mut reader = csv::Reader::from_reader(response.as_bytes().as_ref());
+    let rows = reader
+        .records()
+        .map(|record| record.expect("Expected a valid CSV record"))
+        .collect::<Vec<_>>();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(&rows[0][0], "'-ursula@example.com");
+    assert_eq!(&rows[0][1], "'=1+2");
+    assert_eq!(&rows[1][0], "terry@example.com");
+    assert_eq!(&rows[1][1], "'@SUM");
+}
+
+#[tokio::test]
+async fn importing_confirmed_subscribers_requires_an_attestation() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    app.login().await;
+
+    // Act
+    let response = app
+        .server
+        .post("/admin/subscribers/import")
+        .multipart(import_form(
+            "email,name\nursula@example.com,Ursula\n",
+            "confirmed",
+            false,
+        ))
+        .await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+    assert_eq!(
+        response.header(header::LOCATION),
+        "/admin/subscribers/import"
+    );
+    let form = app.server.get("/admin/subscribers/import").await.text();
+    assert!(form.contains("requires attesting their consent"));
+    assert_eq!(subscriber_status(&app, "ursula@example.com").await, None);
+}
+
+#[tokio::test]
+async fn confirmed_import_reports_invalid_rows_by_line() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    app.login().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .server
+        .post("/admin/subscribers/import")
+        .multipart(import_form(
+            "email,name\nursula@example.com,Ursula\nnot-an-email,Nobody\nterry@example.com,\n",
+            "confirmed",
+            true,
+        ))
+        .await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let report = response.text();
+    assert!(report.contains("1 contacts were imported as confirmed subscribers"));
+    assert!(report.contains("<tr><td>3</td><td>not-an-email</td>"));
+    assert!(report.contains("<tr><td>4</td><td>terry@example.com</td>"));
+    assert_eq!(
+        subscriber_status(&app, "ursula@example.com").await,
+        Some("CONFIRMED".into())
+    );
+    assert_eq!(subscriber_status(&app, "not-an-email").await, None);
+}
+
+#[tokio::test]
+async fn double_opt_in_import_queues_confirmation_emails() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    app.login().await;
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .server
+        .post("/admin/subscribers/import")
+        .multipart(import_form(
+            "email,name\nursula@example.com,Ursula\nterry@example.com,Terry\n",
+            "double_opt_in",
+            false,
+        ))
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert!(response.text().contains("2 contacts were imported"));
+    assert_eq!(
+        subscriber_status(&app, "ursula@example.com").await,
+        Some("PENDING".into())
+    );
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_conformation_links(email_request);
+    let response = app
+        .server
+        .get(&format!(
+            "{}?{}",
+            confirmation_links.html.path(),
+            confirmation_links.html.query().unwrap()
+        ))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn importing_known_emails_leaves_them_untouched() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    app.login().await;
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.server
+        .post("/subscriptions")
+        .form(&[("name", "Ursula"), ("email", "ursula@example.com")])
+        .await;
+
+    // Act
+    let response = app
+        .server
+        .post("/admin/subscribers/import")
+        .multipart(import_form(
+            "email,name\nursula@example.com,Ursula\n",
+            "confirmed",
+            true,
+        ))
+        .await;
+
+    // Assert
+    let report = response.text();
+    assert!(report.contains("0 contacts were imported"));
+    assert!(report.contains("<td>Already known</td>"));
+    assert_eq!(
+        subscriber_status(&app, "ursula@example.com").await,
+        Some("PENDING".into())
+    );
+}
+
+#[tokio::test]
+async fn imports_larger_than_the_limit_are_rejected() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    app.login().await;
+    let row = "ursula@example.com,Ursula\n";
+    let csv = format!("email,name\n{}", row.repeat(6 * 1024 * 1024 / row.len()));
+
+    // Act
+    let response = app
+        .server
+        .post("/admin/subscribers/import")
+        .multipart(import_form(&csv, "confirmed", true))
+        .await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(subscriber_status(&app, "ursula@example.com").await, None);
+}
@@ -0,0 +1,242 @@
+use crate::helpers::TestApp;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+/// Create an API token from the admin area, returning the secret shown once.
+pub async fn create_api_token(app: &TestApp, name: &str, scopes: &[&str]) -> String {
+    let mut form = vec![("name", name)];
+    form.extend(scopes.iter().map(|scope| ("scopes", *scope)));
+    let response = app.server.post("/admin/api-tokens").form(&form).await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+
+    let page = response.text();
+    let start = page.find(r#"<code id="api-token">"#).unwrap() + r#"<code id="api-token">"#.len();
+    let end = start + page[start..].find("</code>").unwrap();
+    page[start..end].to_string()
+}
+
+async fn api_token_key(app: &TestApp, name: &str) -> String {
+    app.state
+        .mm
+        .db()
+        .await
+        .unwrap()
+        .query("SELECT VALUE record::id(id) FROM ONLY api_tokens WHERE name = $name LIMIT 1")
+        .bind(("name", name.to_string()))
+        .await
+        .unwrap()
+        .take::<Option<String>>(0)
+        .unwrap()
+        .expect("Expected the API token to exist")
+}
+
+fn newsletter_body() -> Value {
+    json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as html</p>",
+        },
+    })
+}
+
+#[tokio::test]
+async fn api_tokens_are_shown_once_and_stored_hashed() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    app.login().await;
+
+    // Act
+    let token = create_api_token(&app, "ci", &["newsletter:publish"]).await;
+
+    // Assert
+    assert!(token.starts_with("subs_"));
+    let page = app.server.get("/admin/api-tokens").await.text();
+    assert!(page.contains("ci"));
+    assert!(page.contains("newsletter:publish"));
+    assert!(!page.contains(&token));
+    let stored = app
+        .state
+        .mm
+        .db()
+        .await
+        .unwrap()
+        .query("SELECT VALUE token_hash FROM api_tokens")
+        .await
+        .unwrap()
+        .take::<Vec<String>>(0)
+        .unwrap();
+    assert_eq!(stored.len(), 1);
+    assert!(!stored[0].contains(&token[5..]));
+}
+
+#[tokio::test]
+async fn api_tokens_need_a_name_and_a_known_scope() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    app.login().await;
+
+    for form in [
+        vec![("name", ""), ("scopes", "newsletter:publish")],
+        vec![("name", "ci")],
+        vec![("name", "ci"), ("scopes", "admin:all")],
+    ] {
+        // Act
+        let response = app.server.post("/admin/api-tokens").form(&form).await;
+
+        // Assert
+        assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+        assert_eq!(response.header("Location"), "/admin/api-tokens");
+    }
+}
+
+#[tokio::test]
+async fn bearer_tokens_can_publish_newsletters_and_record_their_last_use() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    app.login().await;
+    let token = create_api_token(&app, "ci", &["newsletter:publish"]).await;
+
+    // Act
+    let legacy = app
+        .server
+        .post("/newsletter")
+        .authorization_bearer(&token)
+        .json(&newsletter_body())
+        .await;
+    let api = app
+        .server
+        .post("/api/v1/newsletters")
+        .authorization_bearer(&token)
+        .json(&newsletter_body())
+        .await;
+
+    // Assert
+    assert_eq!(legacy.status_code(), StatusCode::ACCEPTED);
+    assert_eq!(api.status_code(), StatusCode::ACCEPTED);
+    let last_used_at = app
+        .state
+        .mm
+        .db()
+        .await
+        .unwrap()
+        .query("SELECT VALUE last_used_at FROM ONLY api_tokens LIMIT 1")
+        .await
+        .unwrap()
+        .take::<Option<surrealdb::sql::Datetime>>(0)
+        .unwrap();
+    assert!(last_used_at.is_some(), "Expected the use to be recorded");
+}
+
+#[tokio::test]
+async fn bearer_tokens_only_grant_their_scopes() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    app.login().await;
+    let token = create_api_token(&app, "crm", &["subscribers:read"]).await;
+
+    // Act
+    let lookup = app
+        .server
+        .get("/api/v1/subscribers/unknown")
+        .authorization_bearer(&token)
+        .await;
+    let publish = app
+        .server
+        .post("/api/v1/newsletters")
+        .authorization_bearer(&token)
+        .json(&newsletter_body())
+        .await;
+
+    // Assert
+    assert_eq!(lookup.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(lookup.json::<Value>()["code"], "SUBSCRIBER_NOT_FOUND");
+    assert_eq!(publish.status_code(), StatusCode::FORBIDDEN);
+    assert_eq!(publish.json::<Value>()["code"], "INSUFFICIENT_SCOPE");
+}
+
+#[tokio::test]
+async fn unknown_revoked_and_expired_tokens_are_rejected() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    app.login().await;
+    let revoked = create_api_token(&app, "revoked", &["newsletter:publish"]).await;
+    let expired = create_api_token(&app, "expired", &["newsletter:publish"]).await;
+    let key = api_token_key(&app, "revoked").await;
+    app.server
+        .post(&format!("/admin/api-tokens/{key}/revoke"))
+        .await;
+    app.state
+        .mm
+        .db()
+        .await
+        .unwrap()
+        .query("UPDATE api_tokens SET expires_at = time::now() - 1s WHERE name = 'expired'")
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+
+    for token in ["subs_unknown", revoked.as_str(), expired.as_str()] {
+        // Act
+        let response = app
+            .server
+            .post("/api/v1/newsletters")
+            .authorization_bearer(token)
+            .json(&newsletter_body())
+            .await;
+
+        // Assert
+        assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.json::<Value>()["code"], "UNAUTHORIZED");
+        assert!(
+            response
+                .iter_headers_by_name("WWW-Authenticate")
+                .any(|challenge| challenge == r#"Bearer realm="publish""#)
+        );
+    }
+    let page = app.server.get("/admin/api-tokens").await.text();
+    assert!(page.contains("Revoked"));
+    assert!(page.contains("Expired"));
+}
+
+#[tokio::test]
+async fn revoking_an_unknown_or_revoked_token_warns() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    app.login().await;
+    create_api_token(&app, "revoked", &["newsletter:publish"]).await;
+    let key = api_token_key(&app, "revoked").await;
+    let first = app
+        .server
+        .post(&format!("/admin/api-tokens/{key}/revoke"))
+        .await;
+    assert_eq!(first.status_code(), StatusCode::SEE_OTHER);
+    let page = app.server.get("/admin/api-tokens").await.text();
+    assert!(page.contains("The API token has been revoked"));
+
+    for key in [key.as_str(), "unknown"] {
+        // Act
+        let response = app
+            .server
+            .post(&format!("/admin/api-tokens/{key}/revoke"))
+            .await;
+
+        // Assert
+        assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+        let page = app.server.get("/admin/api-tokens").await.text();
+        assert!(page.contains("The API token was not found or is already revoked"));
+    }
+}
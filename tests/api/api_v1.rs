@@ -0,0 +1,303 @@
+use crate::{
+    helpers::TestApp,
+    newsletter::{
+        create_confirmed_subscriber, create_unconfirmed_subscriber, get_basic_authorization_header,
+    },
+    unsubscribe::unsubscribe_token,
+};
+use reqwest::{Method, StatusCode};
+use serde_json::{Value, json};
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{any, method},
+};
+
+async fn subscriber_id(app: &TestApp) -> String {
+    app.state
+        .mm
+        .db()
+        .await
+        .unwrap()
+        .query("SELECT VALUE record::id(id) FROM ONLY subscriptions WHERE email = 'ursula_le_guin@gmail.com' LIMIT 1")
+        .await
+        .unwrap()
+        .take::<Option<String>>(0)
+        .unwrap()
+        .expect("Expected the subscriber to exist")
+}
+
+#[tokio::test]
+async fn subscribe_accepts_json_and_sends_a_confirmation_email() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .server
+        .post("/api/v1/subscriptions")
+        .json(&json!({ "name": "le guin", "email": "ursula_le_guin@gmail.com" }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+    assert_eq!(
+        response.json::<Value>(),
+        json!({ "data": { "status": "PENDING" } })
+    );
+}
+
+#[tokio::test]
+async fn subscribe_reports_invalid_fields_and_malformed_bodies() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+
+    // Act
+    let invalid = app
+        .server
+        .post("/api/v1/subscriptions")
+        .json(&json!({ "name": "", "email": "ursula_le_guin@gmail.com" }))
+        .await;
+    let malformed = app
+        .server
+        .post("/api/v1/subscriptions")
+        .json(&json!({ "name": "le guin" }))
+        .await;
+
+    // Assert
+    assert_eq!(invalid.status_code(), StatusCode::BAD_REQUEST);
+    let problem = invalid.json::<Value>();
+    assert_eq!(problem["errors"][0]["field"], "name");
+    assert_eq!(problem["errors"][0]["code"], "INVALID_SUBSCRIBER_NAME");
+    assert_eq!(malformed.status_code(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        malformed.json::<Value>()["errors"][0]["code"],
+        "INVALID_BODY"
+    );
+}
+
+#[tokio::test]
+async fn confirm_reports_the_outcome_of_the_token() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    let links = create_unconfirmed_subscriber(&app).await;
+    let token = links
+        .html
+        .query_pairs()
+        .find(|(name, _)| name == "token")
+        .unwrap()
+        .1
+        .into_owned();
+
+    // Act
+    let first = app
+        .server
+        .post("/api/v1/subscriptions/confirm")
+        .json(&json!({ "token": token }))
+        .await;
+    let second = app
+        .server
+        .post("/api/v1/subscriptions/confirm")
+        .json(&json!({ "token": token }))
+        .await;
+    let unknown = app
+        .server
+        .post("/api/v1/subscriptions/confirm")
+        .json(&json!({ "token": "unknown" }))
+        .await;
+
+    // Assert
+    assert_eq!(first.status_code(), StatusCode::OK);
+    assert_eq!(first.json::<Value>()["data"]["status"], "CONFIRMED");
+    assert_eq!(
+        second.json::<Value>()["data"]["status"],
+        "ALREADY_CONFIRMED"
+    );
+    assert_eq!(unknown.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        unknown.json::<Value>()["code"],
+        "INVALID_CONFIRMATION_TOKEN"
+    );
+}
+
+#[tokio::test]
+async fn unsubscribe_accepts_the_token_as_json() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    create_confirmed_subscriber(&app).await;
+    let token = unsubscribe_token(&app).await;
+
+    // Act
+    let response = app
+        .server
+        .post("/api/v1/subscriptions/unsubscribe")
+        .json(&json!({ "token": token }))
+        .await;
+    let unknown = app
+        .server
+        .post("/api/v1/subscriptions/unsubscribe")
+        .json(&json!({ "token": "unknown" }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>(),
+        json!({ "data": { "status": "UNSUBSCRIBED" } })
+    );
+    assert_eq!(unknown.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(unknown.json::<Value>()["code"], "INVALID_UNSUBSCRIBE_TOKEN");
+}
+
+#[tokio::test]
+async fn subscriber_lookup_requires_credentials() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    create_confirmed_subscriber(&app).await;
+    let id = subscriber_id(&app).await;
+
+    // Act
+    let anonymous = app.server.get(&format!("/api/v1/subscribers/{id}")).await;
+    let authenticated = app
+        .server
+        .get(&format!("/api/v1/subscribers/{id}"))
+        .authorization(get_basic_authorization_header(&app.test_user))
+        .await;
+    let unknown = app
+        .server
+        .get("/api/v1/subscribers/unknown")
+        .authorization(get_basic_authorization_header(&app.test_user))
+        .await;
+
+    // Assert
+    assert_eq!(anonymous.status_code(), StatusCode::UNAUTHORIZED);
+    assert_eq!(anonymous.json::<Value>()["code"], "UNAUTHORIZED");
+    assert_eq!(authenticated.status_code(), StatusCode::OK);
+    let data = &authenticated.json::<Value>()["data"];
+    assert_eq!(data["id"], id);
+    assert_eq!(data["email"], "ursula_le_guin@gmail.com");
+    assert_eq!(data["status"], "CONFIRMED");
+    assert_eq!(unknown.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(unknown.json::<Value>()["code"], "SUBSCRIBER_NOT_FOUND");
+}
+
+#[tokio::test]
+async fn publish_newsletter_queues_the_issue_for_confirmed_subscribers() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    create_confirmed_subscriber(&app).await;
+    let newsletter = json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as html</p>",
+        },
+    });
+
+    // Act
+    let anonymous = app
+        .server
+        .post("/api/v1/newsletters")
+        .json(&newsletter)
+        .await;
+    let response = app
+        .server
+        .post("/api/v1/newsletters")
+        .authorization(get_basic_authorization_header(&app.test_user))
+        .json(&newsletter)
+        .await;
+
+    // Assert
+    assert_eq!(anonymous.status_code(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+    assert_eq!(
+        response.json::<Value>(),
+        json!({ "data": { "recipients": 1 } })
+    );
+}
+
+#[tokio::test]
+async fn publish_newsletter_reports_empty_fields() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+
+    // Act
+    let response = app
+        .server
+        .post("/api/v1/newsletters")
+        .authorization(get_basic_authorization_header(&app.test_user))
+        .json(&json!({
+            "title": " ",
+            "content": { "text": "Newsletter body as plain text", "html": "" },
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    let problem = response.json::<Value>();
+    assert_eq!(problem["code"], "VALIDATION_FAILED");
+    assert_eq!(problem["errors"][0]["field"], "content.html");
+    assert_eq!(problem["errors"][0]["code"], "INVALID_NEWSLETTER_CONTENT");
+    assert_eq!(problem["errors"][1]["field"], "title");
+    assert_eq!(problem["errors"][1]["code"], "INVALID_NEWSLETTER_TITLE");
+    assert_eq!(problem["errors"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn idempotency_keys_are_scoped_to_the_endpoint() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    create_confirmed_subscriber(&app).await;
+    let newsletter = json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as html</p>",
+        },
+    });
+    let legacy = app
+        .server
+        .post("/newsletter")
+        .authorization(get_basic_authorization_header(&app.test_user))
+        .add_header("Idempotency-Key", "shared-key")
+        .json(&newsletter)
+        .await;
+    assert_eq!(legacy.status_code(), StatusCode::ACCEPTED);
+
+    // Act
+    let response = app
+        .server
+        .post("/api/v1/newsletters")
+        .authorization(get_basic_authorization_header(&app.test_user))
+        .add_header("Idempotency-Key", "shared-key")
+        .json(&newsletter)
+        .await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+    assert_eq!(
+        response.json::<Value>(),
+        json!({ "data": { "recipients": 1 } })
+    );
+}
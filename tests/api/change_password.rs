@@ -0,0 +1,166 @@
+use crate::helpers::TestApp;
+use reqwest::{StatusCode, header::LOCATION};
+use serde_json::json;
+
+const NEW_PASSWORD: &str = "Correct-horse-battery";
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_change_your_password() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be inissilized!");
+
+    // Act
+    let form = app.server.get("/admin/password").await;
+    let submission = app
+        .server
+        .post("/admin/password")
+        .form(&json!({
+            "current_password": app.test_user.password,
+            "new_password": NEW_PASSWORD,
+            "new_password_check": NEW_PASSWORD,
+        }))
+        .await;
+
+    // Assert
+    for response in [form, submission] {
+        assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+        assert_eq!(response.header(LOCATION), "/login");
+    }
+}
+
+#[tokio::test]
+async fn new_password_fields_must_match() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be inissilized!");
+    app.login().await;
+
+    // Act
+    let response = app
+        .server
+        .post("/admin/password")
+        .form(&json!({
+            "current_password": app.test_user.password,
+            "new_password": NEW_PASSWORD,
+            "new_password_check": "Another-horse-battery",
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.header(LOCATION), "/admin/password");
+    let html_page = app.server.get("/admin/password").await;
+    assert!(html_page.text().contains(
+        "<p><i>You entered two different new passwords - the field values must match.</i></p>"
+    ));
+}
+
+#[tokio::test]
+async fn current_password_must_be_valid() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be inissilized!");
+    app.login().await;
+
+    // Act
+    let response = app
+        .server
+        .post("/admin/password")
+        .form(&json!({
+            "current_password": "wrong-password",
+            "new_password": NEW_PASSWORD,
+            "new_password_check": NEW_PASSWORD,
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.header(LOCATION), "/admin/password");
+    let html_page = app.server.get("/admin/password").await;
+    assert!(
+        html_page
+            .text()
+            .contains("<p><i>The current password is incorrect.</i></p>")
+    );
+}
+
+#[tokio::test]
+async fn weak_new_passwords_are_rejected() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be inissilized!");
+    app.login().await;
+
+    // Act
+    let response = app
+        .server
+        .post("/admin/password")
+        .form(&json!({
+            "current_password": app.test_user.password,
+            "new_password": "short",
+            "new_password_check": "short",
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.header(LOCATION), "/admin/password");
+    let html_page = app.server.get("/admin/password").await;
+    assert!(
+        html_page
+            .text()
+            .contains("<p><i>The new password must be at least 12 characters long</i></p>")
+    );
+}
+
+#[tokio::test]
+async fn changing_password_works() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be inissilized!");
+    app.login().await;
+
+    // Act - Change password
+    let response = app
+        .server
+        .post("/admin/password")
+        .form(&json!({
+            "current_password": app.test_user.password,
+            "new_password": NEW_PASSWORD,
+            "new_password_check": NEW_PASSWORD,
+        }))
+        .await;
+    assert_eq!(response.header(LOCATION), "/admin/password");
+    let html_page = app.server.get("/admin/password").await;
+    assert!(
+        html_page
+            .text()
+            .contains("<p><i>Your password has been changed.</i></p>")
+    );
+
+    // Act - Logout
+    let response = app.server.post("/admin/logout").await;
+    assert_eq!(response.header(LOCATION), "/login");
+    let html_page = app.server.get("/login").await;
+    assert!(
+        html_page
+            .text()
+            .contains("<p><i>You have successfully logged out.</i></p>")
+    );
+
+    // Act - Login using the new password
+    let response = app
+        .server
+        .post("/login")
+        .form(&json!({
+            "username": app.test_user.username,
+            "password": NEW_PASSWORD,
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.header(LOCATION), "/admin/dashboard");
+}
@@ -0,0 +1,177 @@
+use crate::{helpers::TestApp, newsletter::create_confirmed_subscriber};
+use reqwest::{StatusCode, header::LOCATION};
+use serde::Deserialize;
+use wiremock::{Mock, ResponseTemplate, matchers::any};
+
+#[derive(Debug, Deserialize)]
+struct DeadLetter {
+    subscriber_email: String,
+    attempts: u32,
+}
+
+async fn publish_issue(app: &TestApp) {
+    app.state
+        .mm
+        .enqueue_newsletter_issue("Newsletter title", "plain text", "<p>html</p>")
+        .await
+        .expect("Expected the issue to be queued");
+}
+
+async fn dead_letters(app: &TestApp) -> Vec<DeadLetter> {
+    app.state
+        .mm
+        .db()
+        .await
+        .expect("Expected Database to be connected")
+        .query("SELECT subscriber_email, attempts FROM issue_delivery_dead_letters")
+        .await
+        .expect("query should be successful")
+        .take(0)
+        .expect("query result should be valid")
+}
+
+#[tokio::test]
+async fn transient_failures_are_retried_until_delivered() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be inisilized!");
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::INTERNAL_SERVER_ERROR))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    publish_issue(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert!(dead_letters(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn deliveries_are_dead_lettered_after_the_last_attempt() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be inisilized!");
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::SERVICE_UNAVAILABLE))
+        .expect(app.state.config.delivery.max_attempts as u64)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    publish_issue(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let dead_letters = dead_letters(&app).await;
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].subscriber_email, "ursula_le_guin@gmail.com");
+    assert_eq!(
+        dead_letters[0].attempts,
+        app.state.config.delivery.max_attempts
+    );
+}
+
+#[tokio::test]
+async fn permanent_failures_are_not_retried() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be inisilized!");
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::UNPROCESSABLE_ENTITY))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    publish_issue(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let dead_letters = dead_letters(&app).await;
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].attempts, 1);
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_failed_deliveries() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be inisilized!");
+
+    // Act
+    let response = app.server.get("/admin/deliveries/failed").await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+    assert_eq!(response.header(LOCATION), "/login");
+}
+
+#[tokio::test]
+async fn admins_can_replay_failed_deliveries() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be inisilized!");
+    create_confirmed_subscriber(&app).await;
+
+    let failing_mock = Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::UNPROCESSABLE_ENTITY))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    publish_issue(&app).await;
+    app.dispatch_all_pending_emails().await;
+    drop(failing_mock);
+    app.login().await;
+
+    // Act - Part 1 - Inspect the failed deliveries
+    let html_page = app.server.get("/admin/deliveries/failed").await;
+    assert_eq!(html_page.status_code(), StatusCode::OK);
+    assert!(html_page.text().contains("ursula_le_guin@gmail.com"));
+
+    // Act - Part 2 - Replay the failed delivery
+    let text = html_page.text();
+    let start = text
+        .find("/admin/deliveries/failed/")
+        .expect("Expected a replay form");
+    let end = start + text[start..].find('"').unwrap();
+    let replay_path = &text[start..end];
+    let response = app.server.post(replay_path).await;
+    assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+    assert_eq!(response.header(LOCATION), "/admin/deliveries/failed");
+
+    // Assert
+    assert!(dead_letters(&app).await.is_empty());
+    let html_page = app.server.get("/admin/deliveries/failed").await;
+    assert!(
+        html_page
+            .text()
+            .contains("<p><i>The delivery has been queued again</i></p>")
+    );
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+}
@@ -1,4 +1,9 @@
 use crate::helpers::TestApp;
+use reqwest::{Method, StatusCode};
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{any, method, path},
+};
 
 #[tokio::test]
 async fn health_works() {
@@ -14,3 +19,98 @@ async fn health_works() {
     assert!(response.status_code().is_success());
     assert!(response.as_bytes().is_empty());
 }
+
+#[tokio::test]
+async fn liveness_works() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+
+    // Act
+    let response = app.server.get("/health/live").await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn readiness_lists_every_check_when_all_are_up() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    Mock::given(path("/server"))
+        .and(method(Method::GET))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.server.get("/health/ready").await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let body = response.json::<serde_json::Value>();
+    assert_eq!(body["status"], "up");
+    let checks = body["checks"].as_array().unwrap();
+    let names = checks
+        .iter()
+        .map(|check| check["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["database", "migrations", "email"]);
+    for check in checks {
+        assert_eq!(check["status"], "up");
+        assert!(check["latency_ms"].is_u64());
+    }
+}
+
+#[tokio::test]
+async fn readiness_fails_with_503_when_the_email_provider_rejects_the_credentials() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::UNAUTHORIZED))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.server.get("/health/ready").await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+    let body = response.json::<serde_json::Value>();
+    assert_eq!(body["status"], "down");
+    let email = body["checks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|check| check["name"] == "email")
+        .unwrap();
+    assert_eq!(email["status"], "down");
+    assert!(email["error"].as_str().unwrap().contains("401"));
+}
+
+#[tokio::test]
+async fn readiness_reuses_the_outcome_of_the_email_provider_check() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    Mock::given(path("/server"))
+        .and(method(Method::GET))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let first = app.server.get("/health/ready").await;
+    let second = app.server.get("/health/ready").await;
+
+    // Assert
+    assert_eq!(first.status_code(), StatusCode::OK);
+    assert_eq!(second.status_code(), StatusCode::OK);
+}
@@ -1,6 +1,7 @@
 use axum_test::TestServer;
 use std::str::FromStr;
-use subscriptions::{AppState, Config};
+use std::time::Duration;
+use subscriptions::{AppState, Config, EmailProviderConfig, ExecutionOutcome, try_execute_task};
 use tokio::sync::OnceCell;
 use tracing_subscriber::prelude::*;
 use url::Url;
@@ -25,26 +26,40 @@ pub struct ConfirmationLinks {
     pub plain_text: Url,
 }
 
+/// The configuration from `.env.test`, with tracing initialized.
+pub async fn test_config() -> Result<Config> {
+    static TRACING: OnceCell<()> = OnceCell::const_new();
+
+    TRACING
+        .get_or_init(async || {
+            // Load environment variables from .env file if exists
+            dotenvy::from_filename_override(".env.test").ok();
+
+            // Initialize tracing
+            tracing_subscriber::registry()
+                .with(tracing_subscriber::EnvFilter::from_default_env())
+                .with(tracing_subscriber::fmt::layer().with_test_writer())
+                .init();
+        })
+        .await;
+
+    Ok(Config::load()?)
+}
+
 impl TestApp {
     pub async fn new() -> Result<TestApp> {
-        static TRACING: OnceCell<()> = OnceCell::const_new();
-
-        TRACING
-            .get_or_init(async || {
-                // Load environment variables from .env file if exists
-                dotenvy::from_filename_override(".env.test").ok();
-
-                // Initialize tracing
-                tracing_subscriber::registry()
-                    .with(tracing_subscriber::EnvFilter::from_default_env())
-                    .with(tracing_subscriber::fmt::layer().with_test_writer())
-                    .init();
-            })
-            .await;
+        Self::with_config(|_| {}).await
+    }
 
-        let mut config = Config::load()?;
+    /// Spawn an app whose configuration was tweaked by `configure`.
+    pub async fn with_config(configure: impl FnOnce(&mut Config)) -> Result<TestApp> {
+        let mut config = test_config().await?;
         let email_server = MockServer::start().await;
-        config.email_client.base_url = Url::from_str(&email_server.uri())?;
+        config.email_client.provider = EmailProviderConfig::Postmark {
+            base_url: Url::from_str(&email_server.uri())?,
+            auth_token: "token".into(),
+        };
+        configure(&mut config);
 
         let test_user = Credentials {
             password: "password".into(),
@@ -81,6 +96,59 @@ impl TestApp {
         })
     }
 
+    /// Log in as the test user, keeping the session cookie for later requests.
+    pub async fn login(&self) {
+        self.server
+            .post("/login")
+            .form(&[
+                ("username", self.test_user.username.as_str()),
+                ("password", self.test_user.password.as_str()),
+            ])
+            .await;
+    }
+
+    /// Scrape the metrics server of the app.
+    pub async fn get_metrics(&self) -> axum_test::TestResponse {
+        TestServer::new(subscriptions::metrics_router(&self.state))
+            .expect("Expected the metrics server to start")
+            .get("/metrics")
+            .await
+    }
+
+    /// Deliver every queued newsletter issue, waiting for tasks already
+    /// claimed by the background worker to be completed.
+    pub async fn dispatch_all_pending_emails(&self) {
+        loop {
+            let outcome = try_execute_task(
+                &self.state.mm,
+                self.state.email_client.as_ref(),
+                &self.state.config,
+            )
+            .await
+            .expect("Expected the delivery task to be executed");
+
+            if let ExecutionOutcome::EmptyQueue = outcome {
+                let pending = self
+                    .state
+                    .mm
+                    .db()
+                    .await
+                    .unwrap()
+                    .query("RETURN count(SELECT id FROM issue_delivery_queue)")
+                    .await
+                    .unwrap()
+                    .take::<Option<usize>>(0)
+                    .unwrap()
+                    .unwrap_or_default();
+
+                if pending == 0 {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        }
+    }
+
     pub fn get_conformation_links(&self, email_request: &wiremock::Request) -> ConfirmationLinks {
         // Parse the body as json
         let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
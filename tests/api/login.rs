@@ -65,3 +65,29 @@ async fn redirect_to_admin_dashboard_after_login_login_success() {
             .contains(&format!("Welcome {}", app.test_user.username))
     );
 }
+
+#[tokio::test]
+async fn logout_clears_session_state() {
+    // Arrenge
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be inissilized!");
+    app.login().await;
+
+    // Act
+    let response = app.server.post("/admin/logout").await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+    assert_eq!(response.header(LOCATION), "/login");
+    let html_page = app.server.get("/login").await;
+    assert!(
+        html_page
+            .text()
+            .contains("<p><i>You have successfully logged out.</i></p>")
+    );
+
+    let response = app.server.get("/admin/dashboard").await;
+    assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+    assert_eq!(response.header(LOCATION), "/login");
+}
@@ -1,7 +1,22 @@
 mod admin_dashboard;
+mod admin_newsletters;
+mod admin_subscribers;
+mod admin_subscribers_csv;
+mod api_tokens;
+mod api_v1;
+mod change_password;
+mod delivery_retries;
 mod health_check;
 mod helpers;
 mod login;
+mod metrics;
+mod migrations;
 mod newsletter;
+mod openapi;
+mod shutdown;
 mod subscriptions;
 mod subscriptions_confirm;
+mod telemetry;
+mod two_factor;
+mod unsubscribe;
+mod users_cli;
@@ -0,0 +1,141 @@
+use crate::{api_tokens::create_api_token, helpers::TestApp};
+use reqwest::{Method, StatusCode};
+use tokio::net::TcpListener;
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{any, method},
+};
+
+// Metrics are recorded process wide, so tests only look for the series they
+// produced rather than exact values.
+
+#[tokio::test]
+async fn metrics_count_and_time_requests_by_route_and_status() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    app.server.get("/health/live").await;
+
+    // Act
+    let response = app.get_metrics().await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert!(
+        response
+            .header("Content-Type")
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain")
+    );
+    let body = response.text();
+    assert!(body.contains(r#"http_requests_total{method="GET",path="/health/live",status="200"}"#));
+    assert!(body.contains(
+        r#"http_request_duration_seconds_bucket{method="GET",path="/health/live",status="200",le="0.005"}"#
+    ));
+}
+
+#[tokio::test]
+async fn metrics_report_email_sends_subscriptions_and_db_latency() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+    app.server
+        .post("/subscriptions")
+        .form(&[("name", "le guin"), ("email", "ursula_le_guin@gmail.com")])
+        .await;
+
+    // Act
+    let body = app.get_metrics().await.text();
+
+    // Assert
+    assert!(body.contains(r#"email_sends_total{provider="postmark"}"#));
+    assert!(body.contains(r#"email_send_duration_seconds_count{provider="postmark"}"#));
+    assert!(body.contains(r#"subscriptions{status="PENDING"}"#));
+    assert!(body.contains(r#"db_query_duration_seconds_count{operation="create_subscriber"}"#));
+}
+
+#[tokio::test]
+async fn metrics_count_email_send_failures() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::INTERNAL_SERVER_ERROR))
+        .mount(&app.email_server)
+        .await;
+    app.server
+        .post("/subscriptions")
+        .form(&[("name", "le guin"), ("email", "ursula_le_guin@gmail.com")])
+        .await;
+
+    // Act
+    let body = app.get_metrics().await.text();
+
+    // Assert
+    assert!(body.contains(r#"email_send_failures_total{provider="postmark",retryable="true"}"#));
+}
+
+#[tokio::test]
+async fn metrics_are_served_on_a_separate_port_only() {
+    // Arrange
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    let app = TestApp::with_config(|config| {
+        config.metrics.port = Some(listener.local_addr().unwrap().port())
+    })
+    .await
+    .expect("Expected App to be initialized!");
+
+    // Act
+    subscriptions::spawn_metrics_server(listener, &app.state);
+    let on_app_port = app.server.get("/metrics").await;
+    let on_metrics_port = reqwest::get(format!("{address}/metrics"))
+        .await
+        .expect("Expected the metrics server to answer");
+
+    // Assert
+    assert_eq!(on_app_port.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(on_metrics_port.status(), StatusCode::OK);
+    app.state.shutdown.cancel();
+}
+
+#[tokio::test]
+async fn metrics_are_served_to_api_clients_without_a_separate_port() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    app.login().await;
+    let metrics_token = create_api_token(&app, "prometheus", &["metrics:read"]).await;
+    let other_token = create_api_token(&app, "crm", &["subscribers:read"]).await;
+    app.server.post("/admin/logout").await;
+
+    // Act
+    let anonymous = app.server.get("/metrics").await;
+    let other_scope = app
+        .server
+        .get("/metrics")
+        .authorization_bearer(&other_token)
+        .await;
+    let response = app
+        .server
+        .get("/metrics")
+        .authorization_bearer(&metrics_token)
+        .await;
+
+    // Assert
+    assert_eq!(anonymous.status_code(), StatusCode::UNAUTHORIZED);
+    assert_eq!(other_scope.status_code(), StatusCode::FORBIDDEN);
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert!(response.text().contains("http_requests_total"));
+}
@@ -0,0 +1,119 @@
+use crate::helpers::{TestApp, test_config};
+use subscriptions::{ModelManager, cli::MigrateCommand};
+
+/// Every embedded migration, in the order they run.
+const MIGRATIONS: &[&str] = &[
+    "20261018_090000_AddUnsubscribeToken",
+    "20261018_140000_LinkTokensToSubscribers",
+    "20261018_180000_AddIdempotencyKeys",
+    "20261018_180100_AddIssueDeliveryQueue",
+    "20261018_180200_AddDeliveryRetries",
+    "20261018_180300_QueueConfirmationEmails",
+    "20261018_180400_ScopeIdempotencyKeys",
+    "20261018_180500_AddApiTokens",
+    "20261018_180600_AddTwoFactorAuthentication",
+    "20261018_180700_LockSecondFactor",
+    "20261018_180800_AddMetricsScope",
+    "20261018_180900_DeferSubscriberNames",
+];
+const FIRST_MIGRATION: &str = MIGRATIONS[0];
+
+/// The output of `migrate` with the first `applied` migrations applied.
+fn migration_status(applied: usize) -> String {
+    let (applied, pending) = MIGRATIONS.split_at(applied);
+    applied
+        .iter()
+        .map(|name| format!("applied\t{name}\n"))
+        .chain(pending.iter().map(|name| format!("pending\t{name}\n")))
+        .collect()
+}
+
+/// A model manager on a fresh in-memory database, nothing applied yet.
+async fn fresh_database() -> ModelManager {
+    let mut config = test_config().await.expect("Failed to load the config");
+    config.database.auto_migrate = false;
+    ModelManager::new(config.database)
+}
+
+async fn run_migrate_command(mm: &ModelManager, command: MigrateCommand) -> String {
+    let mut output = Vec::new();
+    command
+        .run(mm, &mut output)
+        .await
+        .expect("Expected the command to succeed");
+    String::from_utf8(output).unwrap()
+}
+
+#[tokio::test]
+async fn the_app_refuses_to_start_on_a_schema_behind() {
+    // Act
+    let app = TestApp::with_config(|config| config.database.auto_migrate = false).await;
+
+    // Assert
+    let error = app.err().expect("Expected the app to refuse to start");
+    assert!(error.to_string().contains(FIRST_MIGRATION));
+}
+
+#[tokio::test]
+async fn the_app_migrates_at_startup_by_default() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+
+    // Act
+    let status = app
+        .state
+        .mm
+        .migration_status()
+        .await
+        .expect("Expected the migration status");
+
+    // Assert
+    assert_eq!(status.applied, MIGRATIONS);
+    assert!(status.pending.is_empty());
+}
+
+#[tokio::test]
+async fn migrate_status_lists_pending_migrations() {
+    // Arrange
+    let mm = fresh_database().await;
+
+    // Act
+    let output = run_migrate_command(&mm, MigrateCommand::Status).await;
+
+    // Assert
+    assert_eq!(output, migration_status(0));
+}
+
+#[tokio::test]
+async fn migrate_up_then_down_applies_and_reverts_migrations() {
+    // Arrange
+    let mm = fresh_database().await;
+
+    // Act
+    let up = run_migrate_command(&mm, MigrateCommand::Up).await;
+    let down = run_migrate_command(&mm, MigrateCommand::Down { to: None }).await;
+
+    // Assert
+    assert_eq!(up, migration_status(MIGRATIONS.len()));
+    assert_eq!(down, migration_status(MIGRATIONS.len() - 1));
+    assert!(mm.prepare_schema().await.is_err());
+}
+
+#[tokio::test]
+async fn migrate_down_to_keeps_the_target_migration() {
+    // Arrange
+    let mm = fresh_database().await;
+    run_migrate_command(&mm, MigrateCommand::Up).await;
+
+    // Act
+    let output = run_migrate_command(
+        &mm,
+        MigrateCommand::Down {
+            to: Some(FIRST_MIGRATION.into()),
+        },
+    )
+    .await;
+
+    // Assert
+    assert_eq!(output, migration_status(1));
+}
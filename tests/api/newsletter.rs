@@ -1,6 +1,7 @@
 use base64::{Engine, prelude::BASE64_STANDARD};
 use reqwest::{Method, StatusCode};
 use serde_json::json;
+use std::time::Duration;
 use wiremock::{
     Mock, ResponseTemplate,
     matchers::{any, method},
@@ -8,7 +9,7 @@ use wiremock::{
 
 use crate::helpers::{ConfirmationLinks, Credentials, TestApp};
 
-async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
+pub async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
     let body = [("name", "let guin"), ("email", "ursula_le_guin@gmail.com")];
 
     let _mock_guard = Mock::given(any())
@@ -30,7 +31,7 @@ async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
     app.get_conformation_links(&email_request)
 }
 
-async fn create_confirmed_subscriber(app: &TestApp) {
+pub async fn create_confirmed_subscriber(app: &TestApp) {
     let confirmation_links = create_unconfirmed_subscriber(app).await;
 
     app.server
@@ -43,7 +44,7 @@ async fn create_confirmed_subscriber(app: &TestApp) {
         .assert_status_success();
 }
 
-fn get_basic_authorization_header(user: &Credentials) -> String {
+pub fn get_basic_authorization_header(user: &Credentials) -> String {
     format!(
         "Basic {}",
         BASE64_STANDARD.encode(format!("{}:{}", user.username, user.password))
@@ -74,7 +75,7 @@ async fn newsletter_are_not_delivered_to_unconfirmed_subscribers() {
         .await;
 
     // Assert
-    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
 }
 
 #[tokio::test]
@@ -108,7 +109,9 @@ async fn newsletter_are_delivered_to_confirmed_subscribers() {
         .await;
 
     // Assert
-    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+    app.dispatch_all_pending_emails().await;
+    // Mock verifies on Drop that we have sent the newsletter email
 }
 
 #[tokio::test]
@@ -146,11 +149,15 @@ async fn newsletter_return_400_for_invalid_data() {
 
         // Assert
         assert_eq!(
-            StatusCode::UNPROCESSABLE_ENTITY,
+            StatusCode::BAD_REQUEST,
             response.status_code(),
             "The API did not fail with 400 Bad Request when the payload was {}.",
             error_message
         );
+        assert_eq!(response.header("Content-Type"), "application/problem+json");
+        let problem = response.json::<serde_json::Value>();
+        assert_eq!(problem["code"], "VALIDATION_FAILED");
+        assert_eq!(problem["errors"][0]["code"], "INVALID_BODY");
     }
 }
 
@@ -180,6 +187,14 @@ async fn requests_messing_authorization_are_rejected() {
         r#"Basic realm="publish""#,
         response.header("WWW-Authenticate")
     );
+    assert_eq!(response.header("Content-Type"), "application/problem+json");
+    let problem = response.json::<serde_json::Value>();
+    assert_eq!(problem["status"], 401);
+    assert_eq!(problem["code"], "UNAUTHORIZED");
+    assert!(
+        !problem.to_string().contains("Authorization"),
+        "Expected the cause to stay out of the response"
+    );
 }
 
 #[tokio::test]
@@ -245,3 +260,308 @@ async fn invalid_password_is_rejected() {
         response.header("WWW-Authenticate")
     );
 }
+
+#[tokio::test]
+async fn newsletter_creation_is_idempotent() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be inisilized!");
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter = json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as html</p>",
+        },
+    });
+
+    // Act - Part 1 - Publish the newsletter
+    let response = app
+        .server
+        .post("/newsletter")
+        .authorization(get_basic_authorization_header(&app.test_user))
+        .add_header("Idempotency-Key", "a-unique-key")
+        .json(&newsletter)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+
+    // Act - Part 2 - Retry the same request
+    let retried_response = app
+        .server
+        .post("/newsletter")
+        .authorization(get_basic_authorization_header(&app.test_user))
+        .add_header("Idempotency-Key", "a-unique-key")
+        .json(&newsletter)
+        .await;
+
+    // Assert
+    assert_eq!(retried_response.status_code(), StatusCode::ACCEPTED);
+    assert_eq!(retried_response.as_bytes(), response.as_bytes());
+    app.dispatch_all_pending_emails().await;
+    // Mock verifies on Drop that we have sent the newsletter email **once**
+}
+
+#[tokio::test]
+async fn concurrent_newsletter_submissions_are_sent_once() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be inisilized!");
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter = json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as html</p>",
+        },
+    });
+
+    // Act - Submit two newsletters concurrently
+    let first = app
+        .server
+        .post("/newsletter")
+        .authorization(get_basic_authorization_header(&app.test_user))
+        .add_header("Idempotency-Key", "a-unique-key")
+        .json(&newsletter);
+    let second = app
+        .server
+        .post("/newsletter")
+        .authorization(get_basic_authorization_header(&app.test_user))
+        .add_header("Idempotency-Key", "a-unique-key")
+        .json(&newsletter);
+    let (first, second) = tokio::join!(first, second);
+
+    // Assert
+    let mut statuses = [first.status_code(), second.status_code()];
+    statuses.sort();
+    assert_eq!(statuses[0], StatusCode::ACCEPTED);
+    assert!(
+        statuses[1] == StatusCode::ACCEPTED || statuses[1] == StatusCode::CONFLICT,
+        "unexpected status for the concurrent submission: {}",
+        statuses[1]
+    );
+    app.dispatch_all_pending_emails().await;
+    // Mock verifies on Drop that we have sent the newsletter email **once**
+}
+
+#[tokio::test]
+async fn keys_left_in_progress_are_taken_over_once_their_claim_expires() {
+    // Arrange
+    let app = TestApp::with_config(|config| config.idempotency.claim_ttl = Duration::ZERO)
+        .await
+        .expect("Expected the app to be inisilized!");
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // A request claimed the key and crashed before saving its response
+    let user_id = app
+        .state
+        .mm
+        .get_user_id(&app.test_user.username)
+        .await
+        .unwrap()
+        .expect("Expected the test user to exist");
+    app.state
+        .mm
+        .db()
+        .await
+        .unwrap()
+        .query(
+            r#"
+            CREATE idempotency CONTENT {
+                user: $user,
+                endpoint: 'newsletter',
+                idempotency_key: 'a-unique-key',
+            };
+        "#,
+        )
+        .bind(("user", user_id))
+        .await
+        .unwrap()
+        .check()
+        .expect("Expected the key to be claimed");
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    // Act
+    let response = app
+        .server
+        .post("/newsletter")
+        .authorization(get_basic_authorization_header(&app.test_user))
+        .add_header("Idempotency-Key", "a-unique-key")
+        .json(&json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as html</p>",
+            },
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn expired_idempotency_keys_are_deleted() {
+    // Arrange
+    let app = TestApp::with_config(|config| config.idempotency.key_ttl = Duration::ZERO)
+        .await
+        .expect("Expected the app to be inisilized!");
+    create_confirmed_subscriber(&app).await;
+
+    let response = app
+        .server
+        .post("/newsletter")
+        .authorization(get_basic_authorization_header(&app.test_user))
+        .add_header("Idempotency-Key", "a-unique-key")
+        .json(&json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as html</p>",
+            },
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    // Act
+    let deleted = app
+        .state
+        .mm
+        .delete_expired_idempotency_keys(app.state.config.idempotency.key_ttl)
+        .await
+        .expect("Expected the expired keys to be deleted");
+
+    // Assert
+    assert_eq!(deleted, 1);
+    let remaining: Option<usize> = app
+        .state
+        .mm
+        .db()
+        .await
+        .unwrap()
+        .query("RETURN count(SELECT * FROM idempotency)")
+        .await
+        .unwrap()
+        .take(0)
+        .unwrap();
+    assert_eq!(remaining, Some(0));
+}
+
+#[tokio::test]
+async fn newsletter_rejects_invalid_idempotency_key() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be inisilized!");
+
+    // Act
+    let response = app
+        .server
+        .post("/newsletter")
+        .authorization(get_basic_authorization_header(&app.test_user))
+        .add_header("Idempotency-Key", "a".repeat(51))
+        .json(&json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as html</p>",
+            },
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn newsletter_is_delivered_to_every_confirmed_subscriber_in_the_background() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be inisilized!");
+    create_confirmed_subscriber(&app).await;
+
+    // Act
+    let response = app
+        .server
+        .post("/newsletter")
+        .authorization(get_basic_authorization_header(&app.test_user))
+        .json(&json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as html</p>",
+            },
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn deliveries_abandoned_by_a_dead_worker_are_resumed() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be inisilized!");
+    create_confirmed_subscriber(&app).await;
+    app.state
+        .mm
+        .enqueue_newsletter_issue("Newsletter title", "plain text", "<p>html</p>")
+        .await
+        .expect("Expected the issue to be queued");
+
+    // Act - A worker claims the task and dies before deleting it
+    let claimed = app
+        .state
+        .mm
+        .claim_delivery_task(Duration::ZERO)
+        .await
+        .expect("Expected the task to be claimed");
+    assert!(claimed.is_some());
+
+    // Assert - Once the lock expires the task is handed out again
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    let reclaimed = app
+        .state
+        .mm
+        .claim_delivery_task(Duration::from_secs(60))
+        .await
+        .expect("Expected the task to be claimed");
+    assert!(reclaimed.is_some());
+}
@@ -0,0 +1,157 @@
+use crate::{helpers::TestApp, newsletter::get_basic_authorization_header};
+use reqwest::{Method, StatusCode};
+use serde_json::Value;
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{any, method},
+};
+
+/// Resolve a `#/components/...` reference against the whole `spec`.
+fn resolve<'a>(spec: &'a Value, schema: &'a Value) -> &'a Value {
+    match schema["$ref"].as_str() {
+        Some(reference) => spec
+            .pointer(reference.trim_start_matches('#'))
+            .unwrap_or_else(|| panic!("Expected `{reference}` to be documented")),
+        None => schema,
+    }
+}
+
+/// Build a value of `schema` out of the examples the spec declares.
+fn example(spec: &Value, schema: &Value) -> Value {
+    let schema = resolve(spec, schema);
+    if let Some(example) = schema.get("example").or(schema["examples"].get(0)) {
+        return example.clone();
+    }
+
+    let properties = schema["properties"]
+        .as_object()
+        .unwrap_or_else(|| panic!("Expected an example for {schema}"));
+    properties
+        .iter()
+        .map(|(name, property)| (name.clone(), example(spec, property)))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+#[tokio::test]
+async fn the_openapi_document_is_served() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+
+    // Act
+    let response = app.server.get("/api/openapi.json").await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let spec = response.json::<Value>();
+    assert!(spec["openapi"].as_str().unwrap().starts_with("3.1"));
+    assert!(spec["paths"]["/subscriptions"]["post"].is_object());
+    assert!(spec["paths"]["/newsletter"]["post"].is_object());
+    for schema in ["FormData", "BodyData", "Content", "Problem"] {
+        assert!(
+            spec["components"]["schemas"][schema].is_object(),
+            "Expected `{schema}` to be documented"
+        );
+    }
+}
+
+#[tokio::test]
+async fn the_docs_are_browsable() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+
+    // Act
+    let response = app.server.get("/api/docs").await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert!(response.text().contains("<html"));
+}
+
+#[tokio::test]
+async fn every_documented_operation_accepts_its_documented_request() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+    let spec = app.server.get("/api/openapi.json").await.json::<Value>();
+
+    for (path, operations) in spec["paths"].as_object().unwrap() {
+        for (verb, operation) in operations.as_object().unwrap() {
+            let context = format!("{} {path}", verb.to_uppercase());
+
+            // Act
+            let mut uri = path.clone();
+            let mut query = Vec::new();
+            let mut headers = Vec::new();
+            for parameter in operation["parameters"].as_array().into_iter().flatten() {
+                let name = parameter["name"].as_str().unwrap();
+                let Some(value) = parameter.get("example").and_then(Value::as_str) else {
+                    // Only optional parameters may go without an example
+                    assert_ne!(parameter["required"], true, "{context}: `{name}`");
+                    continue;
+                };
+                match parameter["in"].as_str().unwrap() {
+                    "path" => uri = uri.replace(&format!("{{{name}}}"), value),
+                    "query" => query.push(format!("{name}={value}")),
+                    "header" => headers.push((name.to_owned(), value.to_owned())),
+                    other => panic!("{context}: unexpected `{other}` parameter"),
+                }
+            }
+            if !query.is_empty() {
+                uri = format!("{uri}?{}", query.join("&"));
+            }
+
+            let mut request = app
+                .server
+                .method(verb.to_uppercase().parse().unwrap(), &uri)
+                .add_header(
+                    "Authorization",
+                    get_basic_authorization_header(&app.test_user),
+                );
+            for (name, value) in headers {
+                request = request.add_header(name, value);
+            }
+            if let Some(content) = operation["requestBody"]["content"].as_object() {
+                let (content_type, media) = content.iter().next().unwrap();
+                let body = example(&spec, &media["schema"]);
+                request = match content_type.as_str() {
+                    "application/json" => request.json(&body),
+                    "application/x-www-form-urlencoded" => request.form(&body),
+                    other => panic!("{context}: unexpected `{other}` body"),
+                };
+            }
+            let response = request.await;
+
+            // Assert
+            let status = response.status_code();
+            assert!(
+                !(status == StatusCode::NOT_FOUND && response.as_bytes().is_empty()),
+                "{context}: the route is not served"
+            );
+            let documented = &operation["responses"][status.as_str()];
+            assert!(
+                documented.is_object(),
+                "{context}: answered with undocumented {status}: {}",
+                response.text()
+            );
+            if let Some(content) = documented["content"].as_object() {
+                let content_type = response.header("Content-Type");
+                let content_type = content_type.to_str().unwrap();
+                assert!(
+                    content.keys().any(|media| content_type.starts_with(media)),
+                    "{context}: answered {status} with undocumented `{content_type}`"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{
+    helpers::{TestApp, test_config},
+    newsletter::create_confirmed_subscriber,
+};
+use claims::{assert_ok, assert_some};
+use reqwest::{Method, StatusCode};
+use std::{str::FromStr, time::Duration};
+use subscriptions::{AppState, EmailProviderConfig, run_worker_until_stopped};
+use tokio::{net::TcpListener, sync::oneshot, task::JoinHandle};
+use url::Url;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{any, method},
+};
+
+/// An app served on a random port until the returned sender fires.
+struct ServedApp {
+    address: String,
+    stop: oneshot::Sender<()>,
+    server: JoinHandle<subscriptions::Result<()>>,
+    email_server: MockServer,
+}
+
+async fn serve_app(shutdown_timeout: Duration) -> ServedApp {
+    let email_server = MockServer::start().await;
+    let mut config = test_config().await.expect("Failed to load the config");
+    config.email_client.provider = EmailProviderConfig::Postmark {
+        base_url: Url::from_str(&email_server.uri()).unwrap(),
+        auth_token: "token".into(),
+    };
+    config.shutdown.timeout = shutdown_timeout;
+
+    let (router, state) = subscriptions::init(config)
+        .await
+        .expect("Failed to start the app");
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    let (stop, stopped) = oneshot::channel();
+    let server = tokio::spawn(subscriptions::serve_until_stopped(
+        listener,
+        router,
+        state,
+        async move {
+            stopped.await.ok();
+        },
+    ));
+
+    ServedApp {
+        address,
+        stop,
+        server,
+        email_server,
+    }
+}
+
+/// Mock the email provider, answering after `delay`.
+async fn slow_email_provider(email_server: &MockServer, delay: Duration) {
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK).set_delay(delay))
+        .mount(email_server)
+        .await;
+}
+
+#[tokio::test]
+async fn in_flight_requests_complete_after_a_shutdown_signal() {
+    // Arrange
+    let app = serve_app(Duration::from_secs(5)).await;
+    slow_email_provider(&app.email_server, Duration::from_millis(500)).await;
+    let request = tokio::spawn(
+        reqwest::Client::new()
+            .post(format!("{}/subscriptions", app.address))
+            .form(&[("name", "Ursula"), ("email", "ursula_le_guin@gmail.com")])
+            .send(),
+    );
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Act
+    app.stop.send(()).unwrap();
+
+    // Assert
+    let response = request.await.unwrap().expect("Expected a response");
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let stopped = tokio::time::timeout(Duration::from_secs(5), app.server).await;
+    assert_ok!(assert_ok!(stopped).unwrap());
+    assert!(
+        reqwest::get(format!("{}/health", app.address))
+            .await
+            .is_err(),
+        "Expected new connections to be refused"
+    );
+}
+
+#[tokio::test]
+async fn shutdown_gives_up_on_in_flight_requests_after_the_timeout() {
+    // Arrange
+    let app = serve_app(Duration::from_millis(200)).await;
+    slow_email_provider(&app.email_server, Duration::from_secs(10)).await;
+    tokio::spawn(
+        reqwest::Client::new()
+            .post(format!("{}/subscriptions", app.address))
+            .form(&[("name", "Ursula"), ("email", "ursula_le_guin@gmail.com")])
+            .send(),
+    );
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Act
+    app.stop.send(()).unwrap();
+
+    // Assert
+    let stopped = tokio::time::timeout(Duration::from_secs(2), app.server).await;
+    assert_ok!(assert_ok!(stopped).unwrap());
+}
+
+/// Cancel the background tasks of `state` and wait for them to stop.
+async fn stop_background_tasks(state: &AppState) -> Result<(), tokio::time::error::Elapsed> {
+    state.shutdown.cancel();
+    state.tasks.close();
+    tokio::time::timeout(Duration::from_secs(5), state.tasks.wait()).await
+}
+
+#[tokio::test]
+async fn an_idle_delivery_worker_stops_right_away() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+
+    // Act
+    let stopped = tokio::time::timeout(
+        Duration::from_millis(500),
+        stop_background_tasks(&app.state),
+    )
+    .await;
+
+    // Assert
+    assert_ok!(assert_ok!(stopped));
+}
+
+#[tokio::test]
+async fn the_delivery_worker_finishes_the_email_it_is_sending() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    create_confirmed_subscriber(&app).await;
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK).set_delay(Duration::from_millis(500)))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.state
+        .mm
+        .enqueue_newsletter_issue("Newsletter title", "plain text", "<p>html</p>")
+        .await
+        .expect("Expected the issue to be queued");
+    app.state
+        .tasks
+        .spawn(run_worker_until_stopped(app.state.clone()));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Act
+    let stopped = stop_background_tasks(&app.state).await;
+
+    // Assert
+    assert_ok!(stopped);
+    let outcome = app
+        .state
+        .mm
+        .db()
+        .await
+        .unwrap()
+        .query("SELECT VALUE outcome FROM ONLY issue_delivery_log LIMIT 1")
+        .await
+        .unwrap()
+        .take::<Option<String>>(0)
+        .unwrap();
+    assert_eq!(assert_some!(outcome), "DELIVERED");
+}
@@ -1,4 +1,4 @@
-use crate::helpers::TestApp;
+use crate::{helpers::TestApp, newsletter::create_confirmed_subscriber};
 use reqwest::{Method, StatusCode};
 use serde::Deserialize;
 use wiremock::{
@@ -57,48 +57,113 @@ async fn subscribe_failed() {
         (
             vec![("name", "le guin")],
             "missing the email",
-            StatusCode::UNPROCESSABLE_ENTITY,
+            "INVALID_BODY",
         ),
         (
             vec![("email", "ursula_le_guin@gmail.com")],
             "missing the name",
-            StatusCode::UNPROCESSABLE_ENTITY,
-        ),
-        (
-            vec![],
-            "missing the name and email",
-            StatusCode::UNPROCESSABLE_ENTITY,
+            "INVALID_BODY",
         ),
+        (vec![], "missing the name and email", "INVALID_BODY"),
         (
             vec![("name", ""), ("email", "ursula_le_guin@gmail.com")],
             "empty name",
-            StatusCode::BAD_REQUEST,
+            "INVALID_SUBSCRIBER_NAME",
         ),
         (
             vec![("name", "wow"), ("email", "")],
             "empty email",
-            StatusCode::BAD_REQUEST,
+            "INVALID_SUBSCRIBER_EMAIL",
         ),
         (
             vec![("name", "Ursula"), ("email", "definitely-not-an-email")],
             "invalid email",
-            StatusCode::BAD_REQUEST,
+            "INVALID_SUBSCRIBER_EMAIL",
         ),
     ];
 
-    for (invalid_body, error_message, expected_status) in test_cases {
+    for (invalid_body, error_message, expected_code) in test_cases {
         // Act
         let response = app.server.post("/subscriptions").form(&invalid_body).await;
 
         // Assert
         assert_eq!(
             response.status_code(),
-            expected_status,
-            "The Api did not fail with status {expected_status} when payload was {error_message}."
+            StatusCode::BAD_REQUEST,
+            "The Api did not fail with 400 Bad Request when payload was {error_message}."
+        );
+        assert_eq!(response.header("Content-Type"), "application/problem+json");
+        let problem = response.json::<serde_json::Value>();
+        assert_eq!(problem["code"], "VALIDATION_FAILED");
+        assert_eq!(
+            problem["errors"][0]["code"], expected_code,
+            "The Api did not report {expected_code} when payload was {error_message}."
         );
     }
 }
 
+#[tokio::test]
+async fn subscribe_reports_each_invalid_field_as_a_problem() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+
+    // Act
+    let response = app
+        .server
+        .post("/subscriptions")
+        .add_header("x-request-id", "invalid-subscription")
+        .form(&[("name", ""), ("email", "definitely-not-an-email")])
+        .await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.header("Content-Type"), "application/problem+json");
+    let problem = response.json::<serde_json::Value>();
+    assert_eq!(problem["status"], 400);
+    assert_eq!(problem["code"], "VALIDATION_FAILED");
+    assert_eq!(problem["request_id"], "invalid-subscription");
+    let errors = problem["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0]["field"], "email");
+    assert_eq!(errors[0]["code"], "INVALID_SUBSCRIBER_EMAIL");
+    assert_eq!(errors[1]["field"], "name");
+    assert_eq!(errors[1]["code"], "INVALID_SUBSCRIBER_NAME");
+    assert_eq!(errors[1]["message"], "subscriber name is empty");
+}
+
+#[tokio::test]
+async fn subscribe_failures_do_not_leak_internal_details() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::INTERNAL_SERVER_ERROR))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .server
+        .post("/subscriptions")
+        .form(&[("name", "le guin"), ("email", "ursula_le_guin@gmail.com")])
+        .await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+    let problem = response.json::<serde_json::Value>();
+    assert_eq!(problem["code"], "EMAIL_DELIVERY_FAILED");
+    assert_eq!(problem["detail"], "An unexpected error occurred");
+    assert!(problem["request_id"].is_string());
+    assert!(
+        !problem.to_string().contains(&app.email_server.uri()),
+        "Expected the cause to stay out of the response"
+    );
+}
+
 #[tokio::test]
 pub async fn subscribe_sends_a_confirmation_email_for_valid_data() {
     // Arrange
@@ -189,3 +254,165 @@ pub async fn subscribe_returns_a_200_for_valid_form_data() {
     assert_eq!(result.name, "le guin");
     assert_eq!(result.status, "PENDING");
 }
+
+async fn subscriber_status(app: &TestApp) -> String {
+    app.state
+        .mm
+        .db()
+        .await
+        .expect("Expected Database to be connected")
+        .query("SELECT VALUE status FROM ONLY subscriptions WHERE email = 'ursula_le_guin@gmail.com' LIMIT 1")
+        .await
+        .expect("query should be successful")
+        .take::<Option<String>>(0)
+        .expect("query result should be valid")
+        .expect("query result should to not be empty")
+}
+
+#[tokio::test]
+async fn subscribing_with_a_confirmed_email_sends_an_already_subscribed_notice() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    create_confirmed_subscriber(&app).await;
+    let body = [("name", "le guin"), ("email", "ursula_le_guin@gmail.com")];
+
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.server.post("/subscriptions").form(&body).await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let email: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(email["Subject"], "You're already subscribed");
+    assert_eq!(subscriber_status(&app).await, "CONFIRMED");
+}
+
+#[tokio::test]
+async fn subscribing_after_unsubscribing_starts_a_new_double_opt_in() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    create_confirmed_subscriber(&app).await;
+    app.state
+        .mm
+        .db()
+        .await
+        .expect("Expected Database to be connected")
+        .query("UPDATE subscriptions SET status = 'UNSUBSCRIBED'")
+        .await
+        .expect("query should be successful");
+    let body = [("name", "le guin"), ("email", "ursula_le_guin@gmail.com")];
+
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.server.post("/subscriptions").form(&body).await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+    assert_eq!(subscriber_status(&app).await, "PENDING");
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let link = app.get_conformation_links(&email_request).html;
+    app.server
+        .get(&format!("{}?{}", link.path(), link.query().unwrap()))
+        .await
+        .assert_status_ok();
+    assert_eq!(subscriber_status(&app).await, "CONFIRMED");
+}
+
+#[tokio::test]
+async fn subscribe_responses_do_not_reveal_known_emails() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let known = app
+        .server
+        .post("/subscriptions")
+        .form(&[("name", "le guin"), ("email", "ursula_le_guin@gmail.com")])
+        .await;
+    let unknown = app
+        .server
+        .post("/subscriptions")
+        .form(&[("name", "le guin"), ("email", "another_le_guin@gmail.com")])
+        .await;
+
+    // Assert
+    assert_eq!(known.status_code(), unknown.status_code());
+    assert_eq!(known.as_bytes(), unknown.as_bytes());
+}
+
+#[tokio::test]
+async fn concurrent_subscriptions_with_the_same_email_succeed() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    let body = [("name", "le guin"), ("email", "ursula_le_guin@gmail.com")];
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(5)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let responses = futures_util::future::join_all(
+        (0..5).map(|_| async { app.server.post("/subscriptions").form(&body).await }),
+    )
+    .await;
+
+    // Assert
+    for response in responses {
+        assert_eq!(response.status_code(), StatusCode::CREATED);
+    }
+    let subscriptions = app
+        .state
+        .mm
+        .db()
+        .await
+        .unwrap()
+        .query("SELECT VALUE id FROM subscriptions")
+        .await
+        .unwrap()
+        .take::<Vec<surrealdb::RecordId>>(0)
+        .unwrap();
+    assert_eq!(subscriptions.len(), 1);
+}
@@ -1,6 +1,7 @@
 use crate::helpers::TestApp;
 use reqwest::{Method, StatusCode};
 use serde::Deserialize;
+use std::time::Duration;
 use wiremock::{
     Mock, ResponseTemplate,
     matchers::{any, method},
@@ -73,3 +74,210 @@ async fn confirmation_works() {
     assert_eq!(result.name, "le guin");
     assert_eq!(result.status, "CONFIRMED");
 }
+
+#[tokio::test]
+async fn expired_confirmation_links_show_an_expired_page() {
+    // Arrange
+    let app = TestApp::with_config(|config| {
+        config.confirmation.token_ttl = Duration::from_millis(1);
+    })
+    .await
+    .expect("Expected App to be initialized!");
+    let body = [("name", "le guin"), ("email", "ursula_le_guin@gmail.com")];
+
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+
+    _ = app.server.post("/subscriptions").form(&body).await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_conformation_links(email_request);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // Act
+    let response = app
+        .server
+        .get(&format!(
+            "{}?{}",
+            confirmation_links.html.path(),
+            confirmation_links.html.query().unwrap()
+        ))
+        .await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::GONE);
+    assert!(response.text().contains("expired"));
+    assert_eq!(subscriber_status(&app).await, "PENDING");
+}
+
+#[tokio::test]
+async fn subscribing_again_while_pending_sends_a_fresh_confirmation_link() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    let body = [("name", "le guin"), ("email", "ursula_le_guin@gmail.com")];
+
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let first = app.server.post("/subscriptions").form(&body).await;
+    let second = app.server.post("/subscriptions").form(&body).await;
+
+    // Assert
+    assert_eq!(first.status_code(), StatusCode::CREATED);
+    assert_eq!(second.status_code(), StatusCode::CREATED);
+
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let first_link = app.get_conformation_links(&email_requests[0]).html;
+    let second_link = app.get_conformation_links(&email_requests[1]).html;
+    assert_ne!(first_link, second_link);
+
+    // Only the latest link confirms the subscription
+    app.server
+        .get(&format!(
+            "{}?{}",
+            first_link.path(),
+            first_link.query().unwrap()
+        ))
+        .await;
+    assert_eq!(subscriber_status(&app).await, "PENDING");
+
+    app.server
+        .get(&format!(
+            "{}?{}",
+            second_link.path(),
+            second_link.query().unwrap()
+        ))
+        .await
+        .assert_status_ok();
+    assert_eq!(subscriber_status(&app).await, "CONFIRMED");
+}
+
+#[tokio::test]
+async fn subscribing_again_only_renames_the_subscriber_once_confirmed() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    app.server
+        .post("/subscriptions")
+        .form(&[("name", "le guin"), ("email", "ursula_le_guin@gmail.com")])
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    // Act - Someone else subscribes the same email under another name
+    app.server
+        .post("/subscriptions")
+        .form(&[("name", "mallory"), ("email", "ursula_le_guin@gmail.com")])
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    // Assert
+    assert_eq!(subscriber_name(&app).await, "le guin");
+
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let link = app.get_conformation_links(&email_requests[1]).html;
+    app.server
+        .get(&format!("{}?{}", link.path(), link.query().unwrap()))
+        .await
+        .assert_status_ok();
+    assert_eq!(subscriber_name(&app).await, "mallory");
+}
+
+async fn subscriber_name(app: &TestApp) -> String {
+    app.state
+        .mm
+        .db()
+        .await
+        .expect("Expected Database to be connected")
+        .query("SELECT VALUE name FROM ONLY subscriptions WHERE email = 'ursula_le_guin@gmail.com' LIMIT 1")
+        .await
+        .expect("query should be successful")
+        .take::<Option<String>>(0)
+        .expect("query result should be valid")
+        .expect("query result should to not be empty")
+}
+
+async fn subscriber_status(app: &TestApp) -> String {
+    app.state
+        .mm
+        .db()
+        .await
+        .expect("Expected Database to be connected")
+        .query("SELECT VALUE status FROM ONLY subscriptions WHERE email = 'ursula_le_guin@gmail.com' LIMIT 1")
+        .await
+        .expect("query should be successful")
+        .take::<Option<String>>(0)
+        .expect("query result should be valid")
+        .expect("query result should to not be empty")
+}
+
+#[tokio::test]
+async fn unknown_confirmation_tokens_are_rejected_with_404() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+
+    // Act
+    let response = app.server.get("/subscriptions/confirm?token=unknown").await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+    assert!(
+        response
+            .text()
+            .contains("This confirmation link is invalid")
+    );
+}
+
+#[tokio::test]
+async fn confirming_twice_shows_an_already_confirmed_page() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    let body = [("name", "le guin"), ("email", "ursula_le_guin@gmail.com")];
+
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&app.email_server)
+        .await;
+
+    _ = app.server.post("/subscriptions").form(&body).await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let link = app.get_conformation_links(email_request).html;
+    let path = format!("{}?{}", link.path(), link.query().unwrap());
+
+    // Act
+    let first = app.server.get(&path).await;
+    let second = app.server.get(&path).await;
+
+    // Assert
+    assert_eq!(first.status_code(), StatusCode::OK);
+    assert!(
+        first
+            .text()
+            .contains("Thanks for confirming your subscription")
+    );
+    assert_eq!(second.status_code(), StatusCode::OK);
+    assert!(second.text().contains("already confirmed"));
+    assert_eq!(subscriber_status(&app).await, "CONFIRMED");
+}
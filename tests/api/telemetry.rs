@@ -0,0 +1,116 @@
+use crate::helpers::{TestApp, test_config};
+use claims::{assert_none, assert_ok, assert_some};
+use reqwest::{Method, StatusCode};
+use std::str::FromStr;
+use subscriptions::{init_tracer_provider, otel_layer};
+use tracing_subscriber::{Registry, layer::SubscriberExt};
+use url::Url;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{any, method, path},
+};
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+/// Start an OTLP collector stand-in accepting every batch of spans.
+async fn otlp_collector() -> MockServer {
+    let collector = MockServer::start().await;
+    Mock::given(path("/v1/traces"))
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .mount(&collector)
+        .await;
+    collector
+}
+
+#[tokio::test]
+async fn traces_stay_in_the_process_without_an_otlp_endpoint() {
+    // Arrange
+    let mut config = test_config().await.expect("Failed to load the config");
+    config.tracing.otlp_endpoint = None;
+
+    // Act
+    let provider = init_tracer_provider(&config.tracing);
+
+    // Assert
+    assert_none!(assert_ok!(provider));
+}
+
+#[tokio::test]
+async fn request_spans_are_exported_with_their_request_id_and_remote_parent() {
+    // Arrange
+    let collector = otlp_collector().await;
+    let mut config = test_config().await.expect("Failed to load the config");
+    config.tracing.otlp_endpoint = Some(Url::from_str(&collector.uri()).unwrap());
+    let provider = assert_some!(init_tracer_provider(&config.tracing).unwrap());
+    let _guard = tracing::subscriber::set_default(Registry::default().with(otel_layer(&provider)));
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+
+    // Act
+    app.server
+        .get("/health/live")
+        .add_header("x-request-id", "exported-request-id")
+        .add_header("traceparent", TRACEPARENT)
+        .await;
+    let flushed = tokio::task::spawn_blocking(move || provider.force_flush())
+        .await
+        .unwrap();
+
+    // Assert
+    assert_ok!(flushed);
+    let exported = collector
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .flat_map(|request| request.body)
+        .collect::<Vec<_>>();
+    let contains = |needle: &[u8]| {
+        exported
+            .windows(needle.len())
+            .any(|window| window == needle)
+    };
+    assert!(contains(b"http_request"));
+    assert!(contains(b"exported-request-id"));
+    assert!(contains(&hex::decode(TRACE_ID).unwrap()));
+}
+
+#[tokio::test]
+async fn email_requests_carry_the_trace_of_the_incoming_request() {
+    // Arrange
+    let collector = otlp_collector().await;
+    let mut config = test_config().await.expect("Failed to load the config");
+    config.tracing.otlp_endpoint = Some(Url::from_str(&collector.uri()).unwrap());
+    let provider = assert_some!(init_tracer_provider(&config.tracing).unwrap());
+    let _guard = tracing::subscriber::set_default(Registry::default().with(otel_layer(&provider)));
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.server
+        .post("/subscriptions")
+        .add_header("traceparent", TRACEPARENT)
+        .form(&[("name", "le guin"), ("email", "ursula_le_guin@gmail.com")])
+        .await;
+
+    // Assert
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let traceparent = email_request
+        .headers
+        .get("traceparent")
+        .expect("Expected the trace to be propagated")
+        .to_str()
+        .unwrap();
+    assert!(traceparent.contains(TRACE_ID));
+    assert_ne!(traceparent, TRACEPARENT, "Expected a child span id");
+}
@@ -0,0 +1,350 @@
+use crate::{
+    helpers::TestApp, newsletter::get_basic_authorization_header, users_cli::run_user_command,
+};
+use reqwest::{StatusCode, header::LOCATION};
+use std::time::{SystemTime, UNIX_EPOCH};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+/// The code an authenticator app enrolled with `secret` shows `offset`
+/// seconds from now.
+fn code(secret: &str, offset: u64) -> String {
+    let key = Secret::Encoded(secret.into()).to_bytes().unwrap();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    TOTP::new_unchecked(Algorithm::SHA1, 6, 0, 30, key, None, String::new()).generate(now + offset)
+}
+
+fn between<'a>(page: &'a str, start: &str, end: &str) -> &'a str {
+    let from = page.find(start).expect("Expected the start marker") + start.len();
+    let to = from + page[from..].find(end).expect("Expected the end marker");
+    &page[from..to]
+}
+
+/// Enable two-factor authentication for the test user, then log out.
+///
+/// Returns the TOTP secret and the recovery codes.
+async fn enroll(app: &TestApp) -> (String, Vec<String>) {
+    app.login().await;
+    let page = app.server.get("/admin/two-factor").await.text();
+    let secret = between(&page, r#"<code id="totp-secret">"#, "</code>").to_string();
+
+    let response = app
+        .server
+        .post("/admin/two-factor")
+        .form(&[("code", code(&secret, 0))])
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+    let recovery_codes = between(&response.text(), r#"<ul id="recovery-codes">"#, "</ul>")
+        .split("<li><code>")
+        .filter_map(|item| item.split("</code>").next())
+        .filter(|code| !code.is_empty())
+        .map(str::to_owned)
+        .collect();
+
+    app.server.post("/admin/logout").await;
+    (secret, recovery_codes)
+}
+
+async fn give_password(app: &TestApp) -> axum_test::TestResponse {
+    app.server
+        .post("/login")
+        .form(&[
+            ("username", app.test_user.username.as_str()),
+            ("password", app.test_user.password.as_str()),
+        ])
+        .await
+}
+
+async fn give_code(app: &TestApp, code: &str) -> axum_test::TestResponse {
+    app.server
+        .post("/login/two-factor")
+        .form(&[("code", code)])
+        .await
+}
+
+#[tokio::test]
+async fn enrollment_shows_the_otpauth_uri_and_recovery_codes() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be initialized!");
+
+    // Act
+    let (secret, recovery_codes) = enroll(&app).await;
+
+    // Assert
+    assert_eq!(recovery_codes.len(), 10);
+    give_password(&app).await;
+    give_code(&app, &code(&secret, 30)).await;
+    let page = app.server.get("/admin/two-factor").await.text();
+    assert!(page.contains("enabled, 10 recovery codes left"));
+    assert!(!page.contains(&secret));
+    let stored = app
+        .state
+        .mm
+        .db()
+        .await
+        .unwrap()
+        .query("SELECT VALUE recovery_codes FROM users")
+        .await
+        .unwrap()
+        .take::<Vec<Vec<String>>>(0)
+        .unwrap()
+        .concat();
+    assert_eq!(stored.len(), 10);
+    assert!(stored.iter().all(|hash| !recovery_codes.contains(hash)));
+}
+
+#[tokio::test]
+async fn enrollment_needs_a_valid_first_code() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be initialized!");
+    app.login().await;
+    let page = app.server.get("/admin/two-factor").await.text();
+    let secret = between(&page, r#"<code id="totp-secret">"#, "</code>").to_string();
+    assert!(page.contains(&format!(
+        "otpauth://totp/subscriptions:{}?secret={secret}",
+        app.test_user.username
+    )));
+    assert!(page.contains("<svg"));
+
+    // Act
+    let response = app
+        .server
+        .post("/admin/two-factor")
+        .form(&[("code", "000000")])
+        .await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+    assert_eq!(response.header(LOCATION), "/admin/two-factor");
+    let page = app.server.get("/admin/two-factor").await.text();
+    assert!(page.contains("The code is invalid"));
+    assert!(
+        page.contains(&secret),
+        "Expected the same secret to be kept"
+    );
+    app.server.post("/admin/logout").await;
+    let response = give_password(&app).await;
+    assert_eq!(response.header(LOCATION), "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn enrolled_users_give_a_code_after_their_password() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be initialized!");
+    let (secret, _) = enroll(&app).await;
+
+    // Act
+    let password = give_password(&app).await;
+    let dashboard_before = app.server.get("/admin/dashboard").await;
+    // The enrollment used the current code
+    let second_factor = give_code(&app, &code(&secret, 30)).await;
+
+    // Assert
+    assert_eq!(password.status_code(), StatusCode::SEE_OTHER);
+    assert_eq!(password.header(LOCATION), "/login/two-factor");
+    assert_eq!(dashboard_before.header(LOCATION), "/login");
+    assert_eq!(second_factor.header(LOCATION), "/admin/dashboard");
+    let dashboard = app.server.get("/admin/dashboard").await;
+    assert_eq!(dashboard.status_code(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn enrolled_users_cant_publish_with_basic_credentials() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be initialized!");
+    enroll(&app).await;
+
+    // Act
+    let response = app
+        .server
+        .post("/newsletter")
+        .authorization(get_basic_authorization_header(&app.test_user))
+        .json(&serde_json::json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as html</p>",
+            },
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.json::<serde_json::Value>()["code"], "UNAUTHORIZED");
+}
+
+#[tokio::test]
+async fn codes_cant_be_replayed() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be initialized!");
+    let (secret, _) = enroll(&app).await;
+    let code = code(&secret, 30);
+    give_password(&app).await;
+    give_code(&app, &code).await;
+    app.server.post("/admin/logout").await;
+
+    // Act
+    give_password(&app).await;
+    let response = give_code(&app, &code).await;
+
+    // Assert
+    assert_eq!(response.header(LOCATION), "/login/two-factor");
+    let page = app.server.get("/login/two-factor").await.text();
+    assert!(page.contains("Invalid code"));
+    let dashboard = app.server.get("/admin/dashboard").await;
+    assert_eq!(dashboard.header(LOCATION), "/login");
+}
+
+#[tokio::test]
+async fn recovery_codes_can_be_used_once() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be initialized!");
+    let (_, recovery_codes) = enroll(&app).await;
+
+    // Act
+    give_password(&app).await;
+    let first_use = give_code(&app, &recovery_codes[0].to_uppercase()).await;
+    app.server.post("/admin/logout").await;
+    give_password(&app).await;
+    let second_use = give_code(&app, &recovery_codes[0]).await;
+
+    // Assert
+    assert_eq!(first_use.header(LOCATION), "/admin/dashboard");
+    assert_eq!(second_use.header(LOCATION), "/login/two-factor");
+}
+
+#[tokio::test]
+async fn too_many_invalid_codes_require_the_password_again() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be initialized!");
+    let (secret, _) = enroll(&app).await;
+    give_password(&app).await;
+
+    // Act
+    for _ in 0..4 {
+        let response = give_code(&app, "000000").await;
+        assert_eq!(response.header(LOCATION), "/login/two-factor");
+    }
+    let response = give_code(&app, "000000").await;
+    let valid_code_too_late = give_code(&app, &code(&secret, 30)).await;
+
+    // Assert
+    assert_eq!(response.header(LOCATION), "/login");
+    assert_eq!(valid_code_too_late.header(LOCATION), "/login");
+    let dashboard = app.server.get("/admin/dashboard").await;
+    assert_eq!(dashboard.header(LOCATION), "/login");
+}
+
+#[tokio::test]
+async fn giving_the_password_again_does_not_reset_the_invalid_codes() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be initialized!");
+    let (secret, _) = enroll(&app).await;
+    give_password(&app).await;
+    for _ in 0..4 {
+        give_code(&app, "000000").await;
+    }
+
+    // Act
+    give_password(&app).await;
+    let fifth_invalid_code = give_code(&app, "000000").await;
+    let password_again = give_password(&app).await;
+    let valid_code_while_locked = give_code(&app, &code(&secret, 30)).await;
+
+    // Assert
+    assert_eq!(fifth_invalid_code.header(LOCATION), "/login");
+    assert_eq!(password_again.header(LOCATION), "/login/two-factor");
+    assert_eq!(valid_code_while_locked.header(LOCATION), "/login");
+    let page = app.server.get("/login").await.text();
+    assert!(page.contains("Too many invalid codes, try again later"));
+    let dashboard = app.server.get("/admin/dashboard").await;
+    assert_eq!(dashboard.header(LOCATION), "/login");
+}
+
+#[tokio::test]
+async fn the_second_step_needs_the_password_first() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be initialized!");
+
+    // Act
+    let form = app.server.get("/login/two-factor").await;
+    let code = give_code(&app, "000000").await;
+
+    // Assert
+    assert_eq!(form.header(LOCATION), "/login");
+    assert_eq!(code.header(LOCATION), "/login");
+}
+
+#[tokio::test]
+async fn users_can_disable_two_factor_with_a_code() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be initialized!");
+    let (_, recovery_codes) = enroll(&app).await;
+    give_password(&app).await;
+    give_code(&app, &recovery_codes[0]).await;
+
+    // Act
+    let response = app
+        .server
+        .post("/admin/two-factor/disable")
+        .form(&[("code", recovery_codes[1].as_str())])
+        .await;
+
+    // Assert
+    assert_eq!(response.header(LOCATION), "/admin/two-factor");
+    app.server.post("/admin/logout").await;
+    let response = give_password(&app).await;
+    assert_eq!(response.header(LOCATION), "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn admins_can_reset_the_two_factor_of_a_user() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected the app to be initialized!");
+    enroll(&app).await;
+
+    // Act
+    let output = run_user_command(&app, &["reset-two-factor", &app.test_user.username], "")
+        .await
+        .expect("Expected the reset to succeed");
+
+    // Assert
+    assert_eq!(
+        output,
+        format!(
+            "Reset the two-factor authentication of `{}`\n",
+            app.test_user.username
+        )
+    );
+    let response = give_password(&app).await;
+    assert_eq!(response.header(LOCATION), "/admin/dashboard");
+    assert!(
+        run_user_command(&app, &["reset-two-factor", "nobody"], "")
+            .await
+            .is_err()
+    );
+}
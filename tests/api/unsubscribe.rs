@@ -0,0 +1,335 @@
+use crate::{helpers::TestApp, newsletter::create_confirmed_subscriber};
+use axum_test::multipart::MultipartForm;
+use reqwest::{Method, StatusCode};
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{any, method},
+};
+
+async fn subscriber_status(app: &TestApp) -> String {
+    app.state
+        .mm
+        .db()
+        .await
+        .expect("Expected Database to be connected")
+        .query("SELECT VALUE status FROM ONLY subscriptions WHERE email = 'ursula_le_guin@gmail.com' LIMIT 1")
+        .await
+        .expect("query should be successful")
+        .take::<Option<String>>(0)
+        .expect("query result should be valid")
+        .expect("query result should to not be empty")
+}
+
+pub async fn unsubscribe_token(app: &TestApp) -> String {
+    app.state
+        .mm
+        .db()
+        .await
+        .expect("Expected Database to be connected")
+        .query("SELECT VALUE unsubscribe_token FROM ONLY subscriptions WHERE email = 'ursula_le_guin@gmail.com' LIMIT 1")
+        .await
+        .expect("query should be successful")
+        .take::<Option<String>>(0)
+        .expect("query result should be valid")
+        .expect("query result should to not be empty")
+}
+
+async fn publish_issue(app: &TestApp) {
+    app.state
+        .mm
+        .enqueue_newsletter_issue("Newsletter title", "plain text", "<p>html</p>")
+        .await
+        .expect("Expected the issue to be queued");
+}
+
+#[tokio::test]
+async fn newsletter_emails_contain_an_unsubscribe_link() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    publish_issue(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let unsubscribe_link = format!(
+        "/subscriptions/unsubscribe?token={}",
+        unsubscribe_token(&app).await
+    );
+    assert!(
+        body["HtmlBody"]
+            .as_str()
+            .unwrap()
+            .contains(&unsubscribe_link)
+    );
+    assert!(
+        body["TextBody"]
+            .as_str()
+            .unwrap()
+            .contains(&unsubscribe_link)
+    );
+}
+
+#[tokio::test]
+async fn unsubscribe_link_shows_a_confirmation_page() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    create_confirmed_subscriber(&app).await;
+    let token = unsubscribe_token(&app).await;
+
+    // Act
+    let response = app
+        .server
+        .get(&format!("/subscriptions/unsubscribe?token={token}"))
+        .await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert!(response.text().contains("ursula_le_guin@gmail.com"));
+    assert!(
+        response
+            .text()
+            .contains(r#"<form action="/subscriptions/unsubscribe" method="post">"#)
+    );
+    assert_eq!(subscriber_status(&app).await, "CONFIRMED");
+}
+
+#[tokio::test]
+async fn submitting_the_unsubscribe_form_unsubscribes_the_subscriber() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    create_confirmed_subscriber(&app).await;
+    let token = unsubscribe_token(&app).await;
+
+    // Act
+    let response = app
+        .server
+        .post("/subscriptions/unsubscribe")
+        .form(&[("token", token)])
+        .await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(subscriber_status(&app).await, "UNSUBSCRIBED");
+}
+
+#[tokio::test]
+async fn unsubscribed_subscribers_do_not_receive_newsletters() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    create_confirmed_subscriber(&app).await;
+    publish_issue(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.server
+        .post("/subscriptions/unsubscribe")
+        .form(&[("token", unsubscribe_token(&app).await)])
+        .await
+        .assert_status_ok();
+    publish_issue(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    // Mock verifies on Drop that no newsletter was sent, even for the issue
+    // queued before unsubscribing
+}
+
+#[tokio::test]
+async fn unknown_unsubscribe_tokens_are_rejected_with_404() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+
+    // Act
+    let page = app
+        .server
+        .get("/subscriptions/unsubscribe?token=unknown")
+        .await;
+    let submission = app
+        .server
+        .post("/subscriptions/unsubscribe")
+        .form(&[("token", "unknown")])
+        .await;
+
+    // Assert
+    assert_eq!(page.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(submission.status_code(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn newsletter_emails_carry_one_click_unsubscribe_headers() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(any())
+        .and(method(Method::POST))
+        .respond_with(ResponseTemplate::new(StatusCode::OK))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    publish_issue(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let headers = body["Headers"].as_array().unwrap();
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|header| header["Name"] == name)
+            .and_then(|header| header["Value"].as_str())
+            .unwrap()
+            .to_owned()
+    };
+
+    let one_click_link = format!(
+        "/subscriptions/unsubscribe/one-click?token={}>",
+        unsubscribe_token(&app).await
+    );
+    let list_unsubscribe = header("List-Unsubscribe");
+    let (mailto, one_click) = list_unsubscribe.split_once(", ").unwrap();
+    assert_eq!(
+        mailto,
+        format!(
+            "<mailto:{}?subject=unsubscribe>",
+            app.state.config.email_client.sender_email.as_ref()
+        )
+    );
+    assert!(one_click.starts_with("<http"));
+    assert!(one_click.ends_with(&one_click_link));
+    assert_eq!(
+        header("List-Unsubscribe-Post"),
+        "List-Unsubscribe=One-Click"
+    );
+}
+
+#[tokio::test]
+async fn one_click_unsubscribe_works_without_a_session() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    create_confirmed_subscriber(&app).await;
+    let token = unsubscribe_token(&app).await;
+
+    // Act
+    let response = app
+        .server
+        .post(&format!(
+            "/subscriptions/unsubscribe/one-click?token={token}"
+        ))
+        .form(&[("List-Unsubscribe", "One-Click")])
+        .await;
+
+    // Assert
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(subscriber_status(&app).await, "UNSUBSCRIBED");
+}
+
+#[tokio::test]
+async fn one_click_unsubscribe_accepts_multipart_bodies() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    create_confirmed_subscriber(&app).await;
+    let token = unsubscribe_token(&app).await;
+    let url = format!("/subscriptions/unsubscribe/one-click?token={token}");
+
+    // Act
+    let wrong_body = app
+        .server
+        .post(&url)
+        .multipart(MultipartForm::new().add_text("List-Unsubscribe", "Yes"))
+        .await;
+    let missing_field = app
+        .server
+        .post(&url)
+        .multipart(MultipartForm::new().add_text("Unsubscribe", "One-Click"))
+        .await;
+    let status_before = subscriber_status(&app).await;
+    let response = app
+        .server
+        .post(&url)
+        .multipart(MultipartForm::new().add_text("List-Unsubscribe", "One-Click"))
+        .await;
+
+    // Assert
+    assert_eq!(wrong_body.status_code(), StatusCode::BAD_REQUEST);
+    assert_eq!(missing_field.status_code(), StatusCode::BAD_REQUEST);
+    assert_eq!(status_before, "CONFIRMED");
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(subscriber_status(&app).await, "UNSUBSCRIBED");
+}
+
+#[tokio::test]
+async fn one_click_unsubscribe_rejects_invalid_requests() {
+    // Arrange
+    let app = TestApp::new()
+        .await
+        .expect("Expected App to be initialized!");
+    create_confirmed_subscriber(&app).await;
+    let token = unsubscribe_token(&app).await;
+
+    // Act
+    let wrong_body = app
+        .server
+        .post(&format!(
+            "/subscriptions/unsubscribe/one-click?token={token}"
+        ))
+        .form(&[("List-Unsubscribe", "Yes")])
+        .await;
+    let unknown_token = app
+        .server
+        .post("/subscriptions/unsubscribe/one-click?token=unknown")
+        .form(&[("List-Unsubscribe", "One-Click")])
+        .await;
+
+    // Assert
+    assert_eq!(wrong_body.status_code(), StatusCode::BAD_REQUEST);
+    assert_eq!(unknown_token.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(subscriber_status(&app).await, "CONFIRMED");
+}
@@ -0,0 +1,172 @@
+use crate::helpers::TestApp;
+use claims::assert_err;
+use clap::Parser;
+use reqwest::{StatusCode, header};
+use subscriptions::cli::{Cli, Command, UserCommand};
+
+/// Run `subscriptions user <args>` against the app database, with `input`
+/// as stdin, returning what was written to stdout.
+pub async fn run_user_command(
+    app: &TestApp,
+    args: &[&str],
+    input: &str,
+) -> subscriptions::Result<String> {
+    let cli = Cli::try_parse_from(["subscriptions", "user"].iter().chain(args))
+        .expect("Expected valid arguments");
+    let Some(Command::User(command)) = cli.command else {
+        panic!("Expected a user command");
+    };
+
+    let mut output = Vec::new();
+    command
+        .run(&app.state.mm, &mut input.as_bytes(), &mut output)
+        .await?;
+    Ok(String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn serve_is_the_default_command() {
+    let cli = Cli::try_parse_from(["subscriptions"]).unwrap();
+    assert!(cli.command.is_none());
+
+    let cli = Cli::try_parse_from(["subscriptions", "user", "create", "ops"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::User(UserCommand::Create {
+            password_stdin: false,
+            ..
+        }))
+    ));
+}
+
+#[tokio::test]
+async fn created_users_can_log_in() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+
+    // Act
+    let output = run_user_command(
+        &app,
+        &["create", "ops", "--password-stdin"],
+        "Correct-horse-battery\n",
+    )
+    .await
+    .expect("Expected the user to be created");
+
+    // Assert
+    assert!(output.contains("Created user `ops`"));
+    assert!(!output.contains("Correct-horse-battery"));
+    let response = app
+        .server
+        .post("/login")
+        .form(&[("username", "ops"), ("password", "Correct-horse-battery")])
+        .await;
+    assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+    assert_eq!(response.header(header::LOCATION), "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn a_generated_password_is_printed_once() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+
+    // Act
+    let output = run_user_command(&app, &["create", "ops"], "")
+        .await
+        .expect("Expected the user to be created");
+
+    // Assert
+    let password = output
+        .lines()
+        .find_map(|line| line.strip_prefix("Password: "))
+        .expect("Expected the generated password");
+    let response = app
+        .server
+        .post("/login")
+        .form(&[("username", "ops"), ("password", password)])
+        .await;
+    assert_eq!(response.header(header::LOCATION), "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn weak_passwords_and_taken_usernames_are_rejected() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    let username = app.test_user.username.clone();
+
+    // Act
+    let weak = run_user_command(&app, &["create", "ops", "--password-stdin"], "short\n").await;
+    let taken = run_user_command(&app, &["create", &username], "").await;
+
+    // Assert
+    assert_err!(weak);
+    assert_err!(taken);
+    let users = run_user_command(&app, &["list"], "").await.unwrap();
+    assert_eq!(users.lines().count(), 1);
+}
+
+#[tokio::test]
+async fn reset_password_replaces_the_old_one() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    let username = app.test_user.username.clone();
+
+    // Act
+    run_user_command(
+        &app,
+        &["reset-password", &username, "--password-stdin"],
+        "Correct-horse-battery\n",
+    )
+    .await
+    .expect("Expected the password to be reset");
+
+    // Assert
+    let old_password = app
+        .server
+        .post("/login")
+        .form(&[
+            ("username", username.as_str()),
+            ("password", app.test_user.password.as_str()),
+        ])
+        .await;
+    assert_eq!(old_password.header(header::LOCATION), "/login");
+    let new_password = app
+        .server
+        .post("/login")
+        .form(&[
+            ("username", username.as_str()),
+            ("password", "Correct-horse-battery"),
+        ])
+        .await;
+    assert_eq!(new_password.header(header::LOCATION), "/admin/dashboard");
+
+    assert_err!(run_user_command(&app, &["reset-password", "nobody"], "").await);
+}
+
+#[tokio::test]
+async fn users_can_be_listed_and_deleted() {
+    // Arrange
+    let app = TestApp::new().await.expect("Failed to start test app");
+    let username = app.test_user.username.clone();
+    run_user_command(&app, &["create", "ops"], "")
+        .await
+        .unwrap();
+
+    // Act
+    let before = run_user_command(&app, &["list"], "").await.unwrap();
+    let deleted = run_user_command(&app, &["delete", "ops"], "")
+        .await
+        .unwrap();
+    let after = run_user_command(&app, &["list"], "").await.unwrap();
+
+    // Assert
+    let usernames = |list: &str| {
+        list.lines()
+            .map(|line| line.split('\t').next().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(usernames(&before), ["ops", username.as_str()]);
+    assert!(deleted.contains("Deleted user `ops`"));
+    assert_eq!(usernames(&after), [username.as_str()]);
+    assert_err!(run_user_command(&app, &["delete", "ops"], "").await);
+}